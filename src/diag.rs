@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+
+use crate::lexer::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    /// primary labels point at the problem itself, secondary ones at related code
+    pub primary: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            labels: vec![],
+            notes: vec![],
+            help: None,
+        }
+    }
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+    pub fn primary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: true,
+        });
+        self
+    }
+    pub fn secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: false,
+        });
+        self
+    }
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+    pub fn help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Part of a source line underlined by a label
+struct Underline<'l> {
+    from: usize,
+    to: usize,
+    label: &'l Label,
+    /// whether the label's message goes on this line, which is the last line of its span
+    last: bool,
+}
+
+/// Renders diagnostics with the source lines they point at
+pub struct Renderer<'a> {
+    src: &'a str,
+    path: &'a str,
+    colour: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(src: &'a str, path: &'a str) -> Self {
+        Self {
+            src,
+            path,
+            colour: false,
+        }
+    }
+
+    /// Whether to use ANSI colours
+    pub fn colour(mut self, colour: bool) -> Self {
+        self.colour = colour;
+        self
+    }
+
+    fn paint(&self, colour: &str, text: &str) -> String {
+        if self.colour {
+            format!("{colour}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub fn render(&self, diag: &Diagnostic) -> String {
+        let lines: Vec<&str> = self.src.lines().collect();
+        let mut rows: BTreeMap<usize, Vec<Underline>> = BTreeMap::new();
+        for label in diag.labels.iter() {
+            let (start, end) = (label.span.start, label.span.end);
+            for row in start.row..=end.row.max(start.row) {
+                let line = lines.get(row).copied().unwrap_or("");
+                let from = if row == start.row {
+                    start.col
+                } else {
+                    line.chars().take_while(|c| c.is_whitespace()).count()
+                };
+                let to = if row == end.row {
+                    end.col
+                } else {
+                    line.chars().count()
+                };
+                rows.entry(row).or_default().push(Underline {
+                    from,
+                    // empty spans, e.g. at the end of the input, still get a mark
+                    to: to.max(from + 1),
+                    label,
+                    last: row >= end.row,
+                });
+            }
+        }
+
+        let (severity, colour) = match diag.severity {
+            Severity::Error => ("error", RED),
+        };
+        let width = rows.keys().last().map_or(1, |r| (r + 1).to_string().len());
+        let pad = " ".repeat(width);
+        let gutter = self.paint(BLUE, &format!("{pad} |"));
+
+        let mut out = format!(
+            "{}: {}\n",
+            self.paint(colour, severity),
+            self.paint(BOLD, &diag.message)
+        );
+        if let Some(label) = diag.labels.iter().find(|l| l.primary) {
+            let pos = label.span.start;
+            out += &format!(
+                "{}{}:{}:{}\n",
+                self.paint(BLUE, &format!("{pad}--> ")),
+                self.path,
+                pos.row + 1,
+                pos.col + 1
+            );
+        }
+        if !rows.is_empty() {
+            out += &format!("{gutter}\n");
+        }
+        let mut prev: Option<usize> = None;
+        for (row, underlines) in rows.iter() {
+            if prev.is_some_and(|p| row > &(p + 1)) {
+                out += &format!("{}\n", self.paint(BLUE, "..."));
+            }
+            prev = Some(*row);
+            let line = lines.get(*row).copied().unwrap_or("").replace('\t', " ");
+            let num = self.paint(BLUE, &format!("{:>width$} |", row + 1));
+            out += format!("{num} {line}").trim_end();
+            out += "\n";
+            for u in underlines.iter() {
+                let (mark, colour) = if u.label.primary {
+                    ("^", colour)
+                } else {
+                    ("-", BLUE)
+                };
+                let mut marks = mark.repeat(u.to - u.from);
+                if u.last && !u.label.message.is_empty() {
+                    marks += " ";
+                    marks += &u.label.message;
+                }
+                out += &format!(
+                    "{gutter} {}{}\n",
+                    " ".repeat(u.from),
+                    self.paint(colour, &marks)
+                );
+            }
+        }
+        if !rows.is_empty() && (!diag.notes.is_empty() || diag.help.is_some()) {
+            out += &format!("{gutter}\n");
+        }
+        for note in diag.notes.iter() {
+            out += &format!("{} note: {}\n", self.paint(BLUE, &format!("{pad} =")), note);
+        }
+        if let Some(help) = &diag.help {
+            out += &format!("{} help: {}\n", self.paint(BLUE, &format!("{pad} =")), help);
+        }
+        out
+    }
+}
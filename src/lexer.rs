@@ -1,5 +1,9 @@
+use std::{borrow::Cow, fmt::Display, sync::OnceLock};
+
 use regex::Regex;
 
+use crate::diag::Diagnostic;
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 #[allow(dead_code)]
 pub enum TT {
@@ -17,42 +21,205 @@ pub enum TT {
     Durch,
     Plus,
     Minus,
+    Nöd,
     Funktion,
     Tuen,
     Mit,
     Dä,
+    Veränderlich,
     Isch,
+    Wird,
     Het,
     Git,
     Gib,
     Als,
     Wahr,
     Falsch,
+    Wenn,
+    Suscht,
+    Solang,
+    HörUf,
+    MachWiiter,
+    Für,
+    Vo,
+    Bis,
+    Schritt,
     TypR8,
     TypN8,
     TypZ8,
+    TypR4,
+    TypN16,
+    TypN32,
+    TypN64,
+    TypZ16,
+    TypZ32,
+    TypZ64,
     TypZeiche,
     TypWahrheit,
     Str,       // ".*"
     Id,        //
-    Num,       //
+    Int,       // 42, 0x2a, 0b10_1010
+    Float,     // 4.2, 42e-1
     LBrace,    // {
     RBrace,    // }
+    LParen,    // (
+    RParen,    // )
     Semicolon, // ;
     Comma,     // ,
-    Invalid,   // invalid
+}
+
+/// Position in the source, `row` and `col` are zero based and count chars, `offset` counts bytes
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Pos {
+    pub row: usize,
+    pub col: usize,
+    pub offset: usize,
+}
+
+impl Pos {
+    /// Position right after `text` starting here
+    fn after(self, text: &str) -> Self {
+        match text.rfind('\n') {
+            Some(i) => Self {
+                row: self.row + text.matches('\n').count(),
+                col: text[i + 1..].chars().count(),
+                offset: self.offset + text.len(),
+            },
+            None => Self {
+                row: self.row,
+                col: self.col + text.chars().count(),
+                offset: self.offset + text.len(),
+            },
+        }
+    }
+}
+
+/// Source range from `start` (inclusive) to `end` (exclusive)
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub start: Pos,
+    pub end: Pos,
+}
+
+impl Span {
+    /// Span from the start of `self` to the end of `other`
+    pub fn to(self, other: Span) -> Self {
+        Self {
+            start: self.start,
+            end: other.end,
+        }
+    }
+    /// Empty span right after `self`
+    pub fn after(self) -> Self {
+        Self {
+            start: self.end,
+            end: self.end,
+        }
+    }
+}
+
+// one based, like editors show it
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "row {} col {}", self.start.row + 1, self.start.col + 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexErrorKind<'a> {
+    UnterminatedString,
+    UnterminatedMultiLineString,
+    UnterminatedComment,
+    UnknownChar(char),
+    MalformedNumber(&'a str),
+    InvalidEscape(&'a str),
+}
+
+impl<'a> LexErrorKind<'a> {
+    fn at(self, span: Span) -> LexError<'a> {
+        LexError { kind: self, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError<'a> {
+    pub kind: LexErrorKind<'a>,
+    pub span: Span,
+}
+
+impl<'a> Display for LexErrorKind<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexErrorKind::UnterminatedString | LexErrorKind::UnterminatedMultiLineString => {
+                write!(f, "Unterminated string")
+            }
+            LexErrorKind::UnterminatedComment => write!(f, "Unterminated block comment"),
+            LexErrorKind::UnknownChar(ch) => write!(f, "Unknown character `{}`", ch),
+            LexErrorKind::MalformedNumber(num) => write!(f, "Malformed number `{}`", num),
+            LexErrorKind::InvalidEscape(esc) => write!(f, "Invalid escape sequence `{}`", esc),
+        }
+    }
+}
+
+impl<'a> Display for LexError<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Your code is unreadable at {} :) {}",
+            self.span, self.kind
+        )
+    }
+}
+
+impl<'a> From<&LexError<'a>> for Diagnostic {
+    fn from(err: &LexError<'a>) -> Self {
+        let diag = Diagnostic::error(err.kind.to_string());
+        match err.kind {
+            LexErrorKind::UnterminatedString => diag.primary(err.span, "never closed").help(
+                "close it with `\"` on the same line, or use `\"\"\"` for strings spanning lines",
+            ),
+            LexErrorKind::UnterminatedMultiLineString => diag
+                .primary(err.span, "string starts here")
+                .help("close it with `\"\"\"`"),
+            LexErrorKind::UnterminatedComment => diag
+                .primary(err.span, "comment starts here")
+                .note("block comments nest, so every `/*` needs its own `*/`"),
+            LexErrorKind::UnknownChar(ch) => {
+                let diag = diag.primary(err.span, "not part of the language");
+                let op = match ch {
+                    '+' => "plus",
+                    '-' => "minus",
+                    '!' => "nöd",
+                    '*' => "mal",
+                    '/' => "durch",
+                    '%' => "rescht",
+                    '^' => "hoch",
+                    '<' => "chlinner",
+                    '>' => "grösser",
+                    '=' => "isch`, `wird` or `gliich",
+                    _ => return diag,
+                };
+                diag.help(format!("write `{}` instead", op))
+            }
+            LexErrorKind::MalformedNumber(_) => diag
+                .primary(err.span, "not a number")
+                .note("numbers look like `42`, `-7`, `1_000`, `0xff`, `0b101`, `0.5` or `6.02e23`"),
+            LexErrorKind::InvalidEscape(_) => diag.primary(err.span, "invalid escape").note(
+                "strings know `\\n`, `\\t`, `\\\\`, `\\\"` and `\\u{...}` with 1 to 6 hex digits",
+            ),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Token<'a> {
     pub token_type: TT,
-    pub value: Option<&'a str>,
-    pub row: usize,
-    pub col: usize,
+    /// identifier, number or the decoded contents of a string
+    pub value: Option<Cow<'a, str>>,
+    pub span: Span,
 }
 
-// TODO: use separators instead of space
-const TOKSTR: [&str; 30] = [
+const TOKSTR: [&str; 49] = [
     "gliich",
     "grösser gliich",
     "grösser",
@@ -67,95 +234,266 @@ const TOKSTR: [&str; 30] = [
     "durch",
     "plus",
     "minus",
+    "nöd",
     "funktion",
     "tuen",
     "mit",
     "dä",
+    "veränderlich",
     "isch",
+    "wird",
     "het",
     "git",
     "gib",
     "als",
     "wahr",
     "falsch",
+    "wenn",
+    "suscht",
+    "solang",
+    "hör uf",
+    "mach wiiter",
+    "für",
+    "vo",
+    "bis",
+    "schritt",
     "R8",
     "N8",
     "Z8",
+    "R4",
+    "N16",
+    "N32",
+    "N64",
+    "Z16",
+    "Z32",
+    "Z64",
     "Zeiche",
     "Wahrheit",
 ];
 
-const SEP: [char; 4] = [';', ',', '{', '}'];
+/// The regex for the literal pattern `$re`, compiled once on first use. `None` if it's invalid.
+macro_rules! regex {
+    ($re:literal) => {{
+        static RE: OnceLock<Option<Regex>> = OnceLock::new();
+        RE.get_or_init(|| Regex::new($re).ok()).as_ref()
+    }};
+}
+
+/// Length in bytes of the string literal at the start of `input`, quotes included. Besides
+/// `"..."` there are `"""..."""` strings spanning lines and raw strings `r"..."` and `r"""..."""`
+/// without escapes.
+fn str_len(input: &str) -> Option<usize> {
+    let re = if input.starts_with(r#"r""""#) {
+        regex!(r#"^r"""(?s:.*?)""""#)
+    } else if input.starts_with('r') {
+        regex!(r#"^r"[^"\n]*""#)
+    } else if input.starts_with(r#"""""#) {
+        regex!(r#"^"""([^\\]|\\(?s:.))*?""""#)
+    } else {
+        regex!(r#"^"([^"\\\n]|\\.)*""#)
+    };
+    re?.find(input).map(|m| m.len())
+}
+
+/// Decodes the escape sequences in the contents of a string literal starting at `start`
+fn unescape(raw: &str, start: Pos) -> Result<Cow<'_, str>, LexError<'_>> {
+    if !raw.contains('\\') {
+        return Ok(Cow::Borrowed(raw));
+    }
+    let mut out = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(i) = rest.find('\\') {
+        out.push_str(&rest[..i]);
+        let esc = &rest[i..];
+        let (ch, len) = match esc[1..].chars().next() {
+            Some('n') => (Some('\n'), 2),
+            Some('t') => (Some('\t'), 2),
+            Some('\\') => (Some('\\'), 2),
+            Some('"') => (Some('"'), 2),
+            Some('u') => match esc[2..].strip_prefix('{').and_then(|h| h.split_once('}')) {
+                Some((hex, _)) => {
+                    let ch = Some(hex)
+                        .filter(|h| (1..=6).contains(&h.len()))
+                        .filter(|h| h.chars().all(|c| c.is_ascii_hexdigit()))
+                        .and_then(|h| u32::from_str_radix(h, 16).ok())
+                        .and_then(char::from_u32)
+                        // C strings end at the first NUL
+                        .filter(|c| *c != '\0');
+                    (ch, hex.len() + 4)
+                }
+                None => (None, 2),
+            },
+            Some(c) => (None, c.len_utf8() + 1),
+            None => (None, 1),
+        };
+        let Some(ch) = ch else {
+            let from = start.after(&raw[..raw.len() - rest.len() + i]);
+            let esc = &esc[..len];
+            return Err(LexErrorKind::InvalidEscape(esc).at(Span {
+                start: from,
+                end: from.after(esc),
+            }));
+        };
+        out.push(ch);
+        rest = &esc[len..];
+    }
+    out.push_str(rest);
+    Ok(Cow::Owned(out))
+}
+
+/// Whether `ch` can be part of an identifier, a keyword only ends where an identifier would
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphabetic() || ch.is_ascii_digit() || matches!(ch, '_' | '\'' | '-')
+}
+
+/// Length in bytes of the keyword `kw` at the start of `input`, if it's there. The words of
+/// keywords like `hör uf` can be separated by any whitespace on the same line.
+fn match_keyword(input: &str, kw: &str) -> Option<usize> {
+    let mut rest = input;
+    for (i, word) in kw.split(' ').enumerate() {
+        if i > 0 {
+            let trimmed = rest.trim_start_matches(|c: char| c.is_whitespace() && c != '\n');
+            if trimmed.len() == rest.len() {
+                return None;
+            }
+            rest = trimmed;
+        }
+        rest = rest.strip_prefix(word)?;
+    }
+    if rest.starts_with(is_word_char) {
+        return None;
+    }
+    Some(input.len() - rest.len())
+}
 
 impl<'a> Token<'a> {
-    pub fn from_char(ch: char, row: usize, col: &mut usize) -> Self {
-        *col += 1;
-        Self {
-            token_type: match ch {
-                '{' => TT::LBrace,
-                '}' => TT::RBrace,
-                ';' => TT::Semicolon,
-                ',' => TT::Comma,
-                _ => TT::Invalid,
+    /// Lexes a separator, `None` if `ch` isn't one
+    pub fn from_char(ch: char, start: Pos) -> Option<Self> {
+        let token_type = match ch {
+            '{' => TT::LBrace,
+            '}' => TT::RBrace,
+            '(' => TT::LParen,
+            ')' => TT::RParen,
+            ';' => TT::Semicolon,
+            ',' => TT::Comma,
+            _ => return None,
+        };
+        Some(Self {
+            token_type,
+            span: Span {
+                start,
+                end: start.after(ch.encode_utf8(&mut [0; 4])),
             },
-            row,
-            col: *col - 1,
             value: None,
-        }
+        })
     }
-    fn new(token_type: TT, value: Option<&'a str>, row: usize, col: usize) -> Self {
+    fn new(token_type: TT, value: Option<Cow<'a, str>>, span: Span) -> Self {
         Self {
             token_type,
-            row,
-            col,
+            span,
             value,
         }
     }
-    fn new_builtin(token_type: TT, row: usize, col: usize) -> Self {
+    fn new_builtin(token_type: TT, span: Span) -> Self {
         Self {
             token_type,
-            row,
-            col,
+            span,
             value: None,
         }
     }
-    pub fn from_string(input: &'a str, row: usize, col: &mut usize) -> Self {
-        for (i, t) in TOKSTR.iter().enumerate() {
-            if let Some(next_ch) = input.chars().nth(t.chars().count()) {
-                if input.starts_with(t) && (SEP.contains(&next_ch) || next_ch.is_whitespace()) {
-                    let tok = Token::new_builtin(
-                        unsafe { std::mem::transmute::<u8, TT>(i as u8) },
-                        row,
-                        *col,
-                    );
-                    *col += t.chars().count();
-                    return tok;
-                }
+    /// Lexes a string literal at the start of `input`
+    fn from_str_literal(input: &'a str, start: Pos) -> Result<Self, LexError<'a>> {
+        let raw = input.starts_with('r');
+        let quotes = if input[raw as usize..].starts_with(r#"""""#) {
+            r#"""""#
+        } else {
+            "\""
+        };
+        let open = &input[..raw as usize + quotes.len()];
+        let Some(len) = str_len(input) else {
+            let (kind, text) = if quotes.len() == 3 {
+                (LexErrorKind::UnterminatedMultiLineString, open)
+            } else {
+                let line = input.split('\n').next().unwrap_or(input);
+                (LexErrorKind::UnterminatedString, line.trim_end())
+            };
+            return Err(kind.at(Span {
+                start,
+                end: start.after(text),
+            }));
+        };
+        let lit = &input[..len];
+        let mut content = &lit[open.len()..len - quotes.len()];
+        let mut content_start = start.after(open);
+        // a line break right after the opening quotes isn't part of the string
+        if quotes.len() == 3 {
+            if let Some(c) = content.strip_prefix('\n').or(content.strip_prefix("\r\n")) {
+                content_start = content_start.after(&content[..content.len() - c.len()]);
+                content = c;
             }
         }
-
-        let mut try_find = |re: &str, ttype: TT| -> Option<Token> {
-            if let Ok(re) = Regex::new(re) {
-                if let Some(m) = re.find(input) {
-                    let tok = Token::new(ttype, Some(m.into()), row, *col);
-                    *col += m.as_str().chars().count();
-                    return Some(tok);
-                }
-            }
-            None
+        let value = if raw {
+            Cow::Borrowed(content)
+        } else {
+            unescape(content, content_start)?
+        };
+        Ok(Token::new(
+            TT::Str,
+            Some(value),
+            Span {
+                start,
+                end: start.after(lit),
+            },
+        ))
+    }
+    /// Lexes the token at the start of `input`, its span tells how much of `input` it covers
+    pub fn from_string(input: &'a str, start: Pos) -> Result<Self, LexError<'a>> {
+        let find = |re: Option<&Regex>| -> Option<&'a str> { re?.find(input).map(|m| m.as_str()) };
+        let span_of = |text: &str| Span {
+            start,
+            end: start.after(text),
         };
 
-        if let Some(tok) = try_find(r#"^"([^"]|\\")*""#, TT::Str) {
-            return tok;
+        // the longest keyword wins, e.g. `grösser gliich` over `grösser`
+        let keyword = TOKSTR
+            .iter()
+            .enumerate()
+            .filter_map(|(i, kw)| Some((i, match_keyword(input, kw)?)))
+            .max_by_key(|(_, len)| *len);
+        if let Some((i, len)) = keyword {
+            return Ok(Token::new_builtin(
+                unsafe { std::mem::transmute::<u8, TT>(i as u8) },
+                span_of(&input[..len]),
+            ));
+        }
+
+        if input.starts_with('"') || input.starts_with(r#"r""#) {
+            return Token::from_str_literal(input, start);
         }
-        if let Some(tok) = try_find(r#"^-?[0-9]*([0-9]|([0-9].[0-9]))[0-9]*"#, TT::Num) {
-            return tok;
+        if let Some(num) = find(regex!(r#"^-?[0-9]+"#)) {
+            // the whole run of characters that can't follow a number, like `1.2.3` or `12ab`
+            let word = find(regex!(r#"^-?[0-9]([eE][+-]|[\p{alpha}0-9_.'])*"#)).unwrap_or(num);
+            let int = find(regex!(
+                r#"^-?(0x[0-9a-fA-F](_?[0-9a-fA-F])*|0b[01](_?[01])*|[0-9](_?[0-9])*)"#
+            ));
+            let float = find(regex!(
+                r#"^-?[0-9](_?[0-9])*(\.[0-9](_?[0-9])*)?([eE][+-]?[0-9](_?[0-9])*)?"#
+            ));
+            return match (int, float) {
+                (Some(num), _) if num == word => {
+                    Ok(Token::new(TT::Int, Some(num.into()), span_of(num)))
+                }
+                (_, Some(num)) if num == word => {
+                    Ok(Token::new(TT::Float, Some(num.into()), span_of(num)))
+                }
+                _ => Err(LexErrorKind::MalformedNumber(word).at(span_of(word))),
+            };
         }
-        if let Some(tok) = try_find(r#"^[\p{alpha}_][\p{alpha}0-9_'-]*"#, TT::Id) {
-            return tok;
+        if let Some(id) = find(regex!(r#"^[\p{alpha}_][\p{alpha}0-9_'-]*"#)) {
+            return Ok(Token::new(TT::Id, Some(id.into()), span_of(id)));
         }
-        *col += input.chars().take_while(|c| !c.is_whitespace()).count() + 1;
-        Token::new_builtin(TT::Invalid, row, *col)
+        let ch = input.chars().next().unwrap_or_default();
+        Err(LexErrorKind::UnknownChar(ch).at(span_of(&input[..ch.len_utf8()])))
     }
 }
 
@@ -170,7 +508,9 @@ fn display_tokens(v: &Tokens) -> String {
                 format!(
                     "{:?}{}",
                     t.token_type,
-                    t.value.map_or("".to_string(), |v| format!(" ({})", v))
+                    t.value
+                        .as_ref()
+                        .map_or("".to_string(), |v| format!(" ({})", v))
                 )
             })
             .collect::<Vec<String>>()
@@ -180,38 +520,91 @@ fn display_tokens(v: &Tokens) -> String {
 
 pub struct Lexer<'a> {
     input: &'a str,
-    row: usize,
-    col: usize,
+    pos: Pos,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
         Self {
             input,
-            row: 0,
-            col: 0,
+            pos: Pos::default(),
         }
     }
 
-    pub fn lex(&mut self) -> Tokens<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos.offset..]
+    }
+
+    /// Moves `len` bytes ahead
+    fn advance(&mut self, len: usize) {
+        self.pos = self.pos.after(&self.rest()[..len]);
+    }
+
+    /// Skips a block comment, they nest and can span lines
+    fn skip_block_comment(&mut self) -> Result<(), LexError<'a>> {
+        let open = Span {
+            start: self.pos,
+            end: self.pos.after("/*"),
+        };
+        let mut depth = 0;
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("/*") {
+                depth += 1;
+                self.advance(2);
+            } else if rest.starts_with("*/") {
+                depth -= 1;
+                self.advance(2);
+                if depth == 0 {
+                    return Ok(());
+                }
+            } else if let Some(ch) = rest.chars().next() {
+                self.advance(ch.len_utf8());
+            } else {
+                return Err(LexErrorKind::UnterminatedComment.at(open));
+            }
+        }
+    }
+
+    /// Lexes the whole input. Invalid input is reported as an error and skipped, so the returned
+    /// tokens are everything that could be lexed.
+    pub fn lex(&mut self) -> (Tokens<'a>, Vec<LexError<'a>>) {
         let mut res: Vec<Token<'a>> = Vec::new();
-        self.input.lines().for_each(|l| {
-            while let Some(ch) = l.chars().nth(self.col) {
-                match ch {
-                    _ if SEP.contains(&ch) => {
-                        res.push(Token::from_char(ch, self.row, &mut self.col))
+        let mut errs = vec![];
+        while let Some(ch) = self.rest().chars().next() {
+            let rest = self.rest();
+            let start = self.pos;
+            if rest.starts_with("//") {
+                self.advance(rest.find('\n').unwrap_or(rest.len()));
+            } else if rest.starts_with("/*") {
+                if let Err(err) = self.skip_block_comment() {
+                    errs.push(err);
+                }
+            } else if ch.is_whitespace() {
+                self.advance(ch.len_utf8());
+            } else {
+                let tok =
+                    Token::from_char(ch, start).map_or_else(|| Token::from_string(rest, start), Ok);
+                match tok {
+                    Ok(tok) => {
+                        self.pos = tok.span.end;
+                        res.push(tok);
                     }
-                    _ if ch.is_whitespace() => self.col += 1,
-                    _ => {
-                        let mut l_iter = l.chars();
-                        (&mut l_iter).take(self.col).count();
-                        res.push(Token::from_string(l_iter.as_str(), self.row, &mut self.col))
+                    Err(err) => {
+                        self.pos = match err.kind {
+                            // skip the rest of the string as well
+                            LexErrorKind::InvalidEscape(_) => {
+                                str_len(rest).map_or(err.span.end, |len| start.after(&rest[..len]))
+                            }
+                            // and everything after unclosed quotes
+                            LexErrorKind::UnterminatedMultiLineString => start.after(rest),
+                            _ => err.span.end,
+                        };
+                        errs.push(err);
                     }
                 }
             }
-            self.row += 1;
-            self.col = 0;
-        });
-        res
+        }
+        (res, errs)
     }
 }
@@ -1,5 +1,7 @@
 pub mod compiler;
+pub mod diag;
 pub mod lexer;
 pub mod parser;
 pub mod seman;
 pub mod interm;
+pub mod trans;
@@ -6,10 +6,11 @@
 use std::{
     env,
     fs::{self, create_dir, exists, File},
-    io::{self, Write},
+    io::{self, IsTerminal, Write},
     process::Command,
 };
 mod compiler;
+mod diag;
 mod interm;
 mod lexer;
 mod parser;
@@ -17,19 +18,60 @@ mod seman;
 mod trans;
 use seman::SemanticAnalyzer;
 
-use crate::{lexer::Lexer, parser::Parser, trans::Transpiler};
+use crate::{
+    diag::{Diagnostic, Renderer},
+    lexer::Lexer,
+    parser::Parser,
+    trans::{Overflow, Transpiler},
+};
+
+/// How many errors are reported before giving up, unless `--max-errors` says otherwise
+const MAX_ERRORS: usize = 20;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if let Some(filepath) = env::args().nth(1) {
-        if let Ok(contents) = fs::read_to_string(&filepath) {
-            dothething(&contents);
+    let mut filepath = None;
+    let mut max_errors = MAX_ERRORS;
+    let mut overflow = Overflow::default();
+    for arg in args.iter().skip(1) {
+        if let Some(n) = arg.strip_prefix("--max-errors=") {
+            match n.parse() {
+                Ok(n) => max_errors = n,
+                Err(_) => {
+                    eprintln!("Invalid number of errors `{}'", n);
+                    return;
+                }
+            }
+        } else if let Some(mode) = arg.strip_prefix("--overflow=") {
+            overflow = match mode {
+                "wrap" => Overflow::Wrap,
+                "trap" => Overflow::Trap,
+                "saturate" => Overflow::Saturate,
+                _ => {
+                    eprintln!(
+                        "Invalid overflow mode `{}', expected wrap, trap or saturate",
+                        mode
+                    );
+                    return;
+                }
+            };
+        } else {
+            filepath = Some(arg);
+        }
+    }
+
+    if let Some(filepath) = filepath {
+        if let Ok(contents) = fs::read_to_string(filepath) {
+            dothething(&contents, filepath, max_errors, overflow);
         } else {
             eprintln!("Failed to read file `{}'", &filepath);
         }
     } else {
-        println!("Usage: {} <input-file.hä>", &args[0]);
+        println!(
+            "Usage: {} [--max-errors=<n>] [--overflow=wrap|trap|saturate] <input-file.hä>",
+            &args[0]
+        );
     }
 
     // TODO: remove or implement
@@ -88,7 +130,19 @@ fn write(filename: &str, content: &str) {
     }
 }
 
-fn dothething(input: &str) {
+/// Prints all diagnostics followed by how many there were
+fn report(renderer: &Renderer, diags: Vec<Diagnostic>) {
+    for diag in diags.iter() {
+        eprintln!("{}", renderer.render(diag));
+    }
+    eprintln!(
+        "Found {} error{}",
+        diags.len(),
+        if diags.len() == 1 { "" } else { "s" }
+    );
+}
+
+fn dothething(input: &str, path: &str, max_errors: usize, overflow: Overflow) {
     // TODO: ffi && raylib speedrun
 
     if !exists("./.build").unwrap_or(false) {
@@ -96,26 +150,36 @@ fn dothething(input: &str) {
             eprintln!("Failed to create build dir: {}", err);
         }
     }
+    let colour = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+    let renderer = Renderer::new(input, path).colour(colour);
     //println!("INPUT:\n{input}");
     write("input.hä", input);
-    let toks = Lexer::new(input).lex();
+    let (toks, errs) = Lexer::new(input).lex();
     write("tokens.txt", &format!("{:#?}", toks));
+    if !errs.is_empty() {
+        report(
+            &renderer,
+            errs.iter().take(max_errors).map(Diagnostic::from).collect(),
+        );
+        return;
+    }
     //println!("TOKS:\n{}", toks);
     //FIXME: lifetime of the ast shouldn't be tied to the lifetime of the parser
-    let mut parser = Parser::new(&toks);
+    let mut parser = Parser::new(&toks).max_errors(max_errors);
     let ast = parser.parse();
     //println!("AST:\n{:#?}", ast);
     match ast {
         Ok(ast) => {
             write("ast.txt", &format!("{:#?}", ast));
-            match SemanticAnalyzer::new(&ast).analyze() {
-                Ok(_) => {
-                    let c_99 = Transpiler::new(&ast).generate();
+            match SemanticAnalyzer::new(&ast).max_errors(max_errors).analyze() {
+                Ok(typed) => {
+                    let c_99 = Transpiler::new(&typed).overflow(overflow).generate();
                     write("c99.c", &c_99);
                     match Command::new("gcc")
                         .arg("./.build/c99.c")
                         .arg("-o")
                         .arg("./.build/out")
+                        .arg("-lm")
                         .output()
                     {
                         Ok(out) => {
@@ -144,9 +208,9 @@ fn dothething(input: &str) {
                     //     Err(err) => eprintln!("Failed to link executable: {}", err),
                     // }
                 }
-                Err(err) => eprintln!("Failed to analyze: {}", err),
+                Err(errs) => report(&renderer, errs.iter().map(Diagnostic::from).collect()),
             }
         }
-        Err(err) => eprintln!("Failed to parse: {}", err),
+        Err(errs) => report(&renderer, errs.iter().map(Diagnostic::from).collect()),
     }
 }
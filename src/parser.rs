@@ -1,21 +1,42 @@
-use std::fmt::Display;
+use std::{cell::Cell, fmt::Display};
 
-use crate::lexer::{Token, Tokens, TT};
+use crate::{
+    diag::Diagnostic,
+    lexer::{Span, Token, Tokens, TT},
+};
 
 pub type Program<'a> = Vec<Stmt<'a>>;
+/// Index of a declaration in the table built by the semantic analyzer
+pub type DeclId = usize;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PrimType {
     String,
+    /// 8 byte float
     R8,
+    /// 4 byte float
+    R4,
+    /// unsigned integers, the number is the size in bits
     N8,
+    N16,
+    N32,
+    N64,
+    /// signed integers, the number is the size in bits
     Z8,
+    Z16,
+    Z32,
+    Z64,
     Boolean,
 }
 
+/// The type as it's written in Haessig
 impl Display for PrimType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            PrimType::String => write!(f, "Zeiche"),
+            PrimType::Boolean => write!(f, "Wahrheit"),
+            pt => write!(f, "{:?}", pt),
+        }
     }
 }
 
@@ -26,13 +47,54 @@ impl PrimType {
             TT::TypR8 => Some(PrimType::R8),
             TT::TypN8 => Some(PrimType::N8),
             TT::TypZ8 => Some(PrimType::Z8),
+            TT::TypR4 => Some(PrimType::R4),
+            TT::TypN16 => Some(PrimType::N16),
+            TT::TypN32 => Some(PrimType::N32),
+            TT::TypN64 => Some(PrimType::N64),
+            TT::TypZ16 => Some(PrimType::Z16),
+            TT::TypZ32 => Some(PrimType::Z32),
+            TT::TypZ64 => Some(PrimType::Z64),
             TT::TypWahrheit => Some(PrimType::Boolean),
             _ => None,
         }
     }
+    pub fn is_num(&self) -> bool {
+        self.is_int() || self.is_float()
+    }
+    pub fn is_int(&self) -> bool {
+        self.int_range().is_some()
+    }
+    pub fn is_float(&self) -> bool {
+        matches!(self, PrimType::R4 | PrimType::R8)
+    }
+    pub fn is_signed(&self) -> bool {
+        self.int_range().is_some_and(|(min, _)| min < 0)
+    }
+    /// Smallest and largest value of an integer type
+    pub fn int_range(&self) -> Option<(i128, i128)> {
+        match self {
+            PrimType::N8 => Some((u8::MIN.into(), u8::MAX.into())),
+            PrimType::N16 => Some((u16::MIN.into(), u16::MAX.into())),
+            PrimType::N32 => Some((u32::MIN.into(), u32::MAX.into())),
+            PrimType::N64 => Some((u64::MIN.into(), u64::MAX.into())),
+            PrimType::Z8 => Some((i8::MIN.into(), i8::MAX.into())),
+            PrimType::Z16 => Some((i16::MIN.into(), i16::MAX.into())),
+            PrimType::Z32 => Some((i32::MIN.into(), i32::MAX.into())),
+            PrimType::Z64 => Some((i64::MIN.into(), i64::MAX.into())),
+            _ => None,
+        }
+    }
+    /// Largest finite value of a float type
+    pub fn float_max(&self) -> Option<f64> {
+        match self {
+            PrimType::R4 => Some(f32::MAX.into()),
+            PrimType::R8 => Some(f64::MAX),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BinOperator {
     Gliich,
     GrösserGliich,
@@ -50,6 +112,31 @@ pub enum BinOperator {
     Minus,
 }
 
+impl Display for BinOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                BinOperator::Gliich => "gliich",
+                BinOperator::GrösserGliich => "grösser gliich",
+                BinOperator::Grösser => "grösser",
+                BinOperator::ChlinnerGliich => "chlinner gliich",
+                BinOperator::Chlinner => "chlinner",
+                BinOperator::Ungliich => "ungliich",
+                BinOperator::Und => "und",
+                BinOperator::Oder => "oder",
+                BinOperator::Rescht => "rescht",
+                BinOperator::Hoch => "hoch",
+                BinOperator::Mal => "mal",
+                BinOperator::Durch => "durch",
+                BinOperator::Plus => "plus",
+                BinOperator::Minus => "minus",
+            }
+        )
+    }
+}
+
 impl BinOperator {
     fn from_tt(value: TT) -> Option<Self> {
         match value {
@@ -70,14 +157,87 @@ impl BinOperator {
             _ => None,
         }
     }
+    /// Binding power of the operator, higher binds tighter
+    fn precedence(&self) -> u8 {
+        match self {
+            BinOperator::Oder => 1,
+            BinOperator::Und => 2,
+            BinOperator::Gliich
+            | BinOperator::Ungliich
+            | BinOperator::GrösserGliich
+            | BinOperator::Grösser
+            | BinOperator::ChlinnerGliich
+            | BinOperator::Chlinner => 3,
+            BinOperator::Plus | BinOperator::Minus => 4,
+            BinOperator::Rescht | BinOperator::Mal | BinOperator::Durch => 5,
+            BinOperator::Hoch => 6,
+        }
+    }
+    fn is_right_assoc(&self) -> bool {
+        matches!(self, BinOperator::Hoch)
+    }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnOperator {
+    Minus,
+    Nöd,
+}
+
+impl Display for UnOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                UnOperator::Minus => "minus",
+                UnOperator::Nöd => "nöd",
+            }
+        )
+    }
+}
+
+impl UnOperator {
+    fn from_tt(value: TT) -> Option<Self> {
+        match value {
+            TT::Minus => Some(UnOperator::Minus),
+            TT::Nöd => Some(UnOperator::Nöd),
+            _ => None,
+        }
+    }
+    /// Binding power of the operand: `minus` binds tighter than `mal` but looser than `hoch`,
+    /// `nöd` tighter than `und` but looser than comparisons
+    fn operand_precedence(&self) -> u8 {
+        match self {
+            UnOperator::Minus => BinOperator::Hoch.precedence(),
+            UnOperator::Nöd => BinOperator::Gliich.precedence(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Un<'a> {
+    pub expr: Box<Expr<'a>>,
+    pub op: UnOperator,
+}
 #[derive(Debug, Clone)]
 pub struct Bin<'a> {
     pub lhs: Box<Expr<'a>>,
     pub rhs: Box<Expr<'a>>,
     pub op: BinOperator,
 }
+/// Conversion of `expr` to `pt` with `als`
+#[derive(Debug, Clone)]
+pub struct Cast<'a> {
+    pub expr: Box<Expr<'a>>,
+    pub pt: PrimType,
+}
+#[derive(Debug, Clone)]
+pub struct Cond<'a> {
+    pub cond: Box<Expr<'a>>,
+    pub then: Box<Expr<'a>>,
+    pub els: Box<Expr<'a>>,
+}
 #[derive(Debug, Clone)]
 pub enum StEx<'a> {
     Call(Call<'a>),
@@ -87,30 +247,83 @@ pub enum StEx<'a> {
 pub enum Stmt<'a> {
     FunAss(FunAss<'a>),
     VarAss(VarAss<'a>),
+    Reass(Reass<'a>),
     StEx(StEx<'a>),
     Ret(Ret<'a>),
+    If(If<'a>),
+    While(While<'a>),
+    For(Box<For<'a>>),
+    Break(Span),
+    Continue(Span),
 }
 #[derive(Debug, Clone)]
-pub enum Expr<'a> {
+pub struct Expr<'a> {
+    pub kind: ExprKind<'a>,
+    pub span: Span,
+    /// type of the expression, filled in by the semantic analyzer
+    pub ty: Cell<Option<PrimType>>,
+}
+#[derive(Debug, Clone)]
+pub enum ExprKind<'a> {
     StEx(StEx<'a>),
     Prim(Prim<'a>),
+    Un(Un<'a>),
     Bin(Bin<'a>),
+    Cond(Cond<'a>),
+    Cast(Cast<'a>),
+}
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct If<'a> {
+    pub cond: Expr<'a>,
+    pub then: Block<'a>,
+    pub els: Option<Else<'a>>,
+    pub span: Span,
+}
+#[derive(Debug, Clone)]
+pub enum Else<'a> {
+    If(Box<If<'a>>),
+    Block(Block<'a>),
+}
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct While<'a> {
+    pub cond: Expr<'a>,
+    pub body: Block<'a>,
+    pub span: Span,
+}
+#[derive(Debug, Clone)]
+pub struct For<'a> {
+    pub id: &'a str,
+    pub from: Expr<'a>,
+    pub to: Expr<'a>,
+    pub step: Option<Expr<'a>>,
+    pub body: Block<'a>,
+    /// type of the loop variable, filled in by the semantic analyzer
+    pub ty: Cell<Option<PrimType>>,
+    pub decl: Cell<Option<DeclId>>,
+    pub span: Span,
 }
 #[derive(Debug, Clone)]
 pub struct Ret<'a> {
     pub expr: Expr<'a>,
+    pub span: Span,
 }
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub enum Prim<'a> {
     Bool(bool),
     Str(&'a str),
-    R8(u8),
-    Id(&'a str),
+    /// integer literal, wide enough for every integer type
+    Int(i128),
+    Float(f64),
+    /// identifier and the declaration it resolves to
+    Id(&'a str, Cell<Option<DeclId>>),
 }
 #[derive(Debug, Clone)]
 pub struct Block<'a> {
     pub stmts: Vec<Stmt<'a>>,
+    pub span: Span,
 }
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -118,11 +331,26 @@ pub struct VarAss<'a> {
     pub id: &'a str,
     pub value: Expr<'a>,
     pub pt: Option<PrimType>,
+    pub mutable: bool,
+    /// type of the variable, either `pt` or inferred from `value`, filled in by the semantic
+    /// analyzer
+    pub ty: Cell<Option<PrimType>>,
+    pub decl: Cell<Option<DeclId>>,
+    pub span: Span,
+}
+#[derive(Debug, Clone)]
+pub struct Reass<'a> {
+    pub id: &'a str,
+    pub value: Expr<'a>,
+    pub decl: Cell<Option<DeclId>>,
+    pub span: Span,
 }
 #[derive(Debug, Clone)]
 pub struct Arg<'a> {
     pub id: &'a str,
     pub pt: PrimType,
+    pub decl: Cell<Option<DeclId>>,
+    pub span: Span,
 }
 #[derive(Debug, Clone)]
 pub struct FunAss<'a> {
@@ -130,54 +358,124 @@ pub struct FunAss<'a> {
     pub body: Block<'a>,
     pub ret: Option<PrimType>,
     pub args: Vec<Arg<'a>>,
+    pub decl: Cell<Option<DeclId>>,
+    pub span: Span,
 }
 #[derive(Debug, Clone)]
 pub struct Call<'a> {
     pub id: &'a str,
     pub args: Vec<Expr<'a>>,
+    /// declaration of the called function, `None` for builtins
+    pub decl: Cell<Option<DeclId>>,
+    pub span: Span,
+}
+
+#[allow(dead_code)]
+impl<'a> Stmt<'a> {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::FunAss(fun_ass) => fun_ass.span,
+            Stmt::VarAss(var_ass) => var_ass.span,
+            Stmt::Reass(reass) => reass.span,
+            Stmt::StEx(st_ex) => st_ex.span(),
+            Stmt::Ret(ret) => ret.span,
+            Stmt::If(if_stmt) => if_stmt.span,
+            Stmt::While(while_stmt) => while_stmt.span,
+            Stmt::For(for_stmt) => for_stmt.span,
+            Stmt::Break(span) | Stmt::Continue(span) => *span,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl<'a> StEx<'a> {
+    pub fn span(&self) -> Span {
+        match self {
+            StEx::Call(call) => call.span,
+            StEx::Block(block) => block.span,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum ParseError<'a> {
-    NoTokensLeft,
+    NoTokensLeft(Span),
     UnexpectedToken(String, Token<'a>),
     ExpectedToken(String, TT, Token<'a>),
-    ExpectedOperator(String, Token<'a>),
     ExpectedType(String, Token<'a>),
     ExpectedPrim(String, Token<'a>),
     MissingValue(String, TT, Token<'a>),
-    NotConvertible(String, TT, PrimType, Token<'a>),
+    NumberTooLarge(String, Token<'a>),
+}
+
+impl<'a> From<&ParseError<'a>> for Diagnostic {
+    fn from(err: &ParseError<'a>) -> Self {
+        let got = |t: &Token| {
+            t.value
+                .as_ref()
+                .map_or(format!("{:?}", t.token_type), |v| format!("`{}`", v))
+        };
+        let (diag, n) = match err {
+            ParseError::NoTokensLeft(span) => {
+                return Diagnostic::error("Unexpected end of input")
+                    .primary(*span, "expected more code here")
+            }
+            ParseError::UnexpectedToken(n, t) => (
+                Diagnostic::error(format!("Unexpected {}", got(t))).primary(t.span, "unexpected"),
+                n,
+            ),
+            ParseError::ExpectedToken(n, tt, t) => (
+                Diagnostic::error(format!("Expected {:?}, got {}", tt, got(t)))
+                    .primary(t.span, format!("expected {:?}", tt)),
+                n,
+            ),
+            ParseError::ExpectedType(n, t) => (
+                Diagnostic::error(format!("Expected a type, got {}", got(t)))
+                    .primary(t.span, "expected a type")
+                    .help("types are N8 to N64, Z8 to Z64, R4, R8, Zeiche and Wahrheit"),
+                n,
+            ),
+            ParseError::ExpectedPrim(n, t) => (
+                Diagnostic::error(format!("Expected a value, got {}", got(t)))
+                    .primary(t.span, "expected a value"),
+                n,
+            ),
+            ParseError::MissingValue(n, tt, t) => (
+                Diagnostic::error(format!("{:?} without a value", tt)).primary(t.span, ""),
+                n,
+            ),
+            ParseError::NumberTooLarge(n, t) => (
+                Diagnostic::error(format!("Number {} is too large", got(t)))
+                    .primary(t.span, "doesn't fit any number type"),
+                n,
+            ),
+        };
+        diag.note(format!("while parsing {}", n))
+    }
 }
 
 impl<'a> Display for ParseError<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let fm = |n, t: &Token<'a>| {
             format!(
-                "Your code is bonkers at row {} col {} parsing {} :) Got type {:?} with value {:?}, ",
-                t.row, t.col, n, t.token_type, t.value
+                "Your code is bonkers at {} parsing {} :) Got type {:?} with value {:?}, ",
+                t.span, n, t.token_type, t.value
             )
         };
         match self {
-            ParseError::NoTokensLeft => write!(f, "Your code is bonkers. No tokens left"),
+            ParseError::NoTokensLeft(span) => {
+                write!(f, "Your code is bonkers at {}. No tokens left", span)
+            }
             ParseError::UnexpectedToken(n, token) => write!(f, "{}unexpected token", fm(n, token)),
             ParseError::ExpectedToken(n, tt, token) => {
                 write!(f, "{}expected token {:?}", fm(n, token), tt)
             }
             ParseError::ExpectedType(n, token) => write!(f, "{}expected type", fm(n, token)),
-            ParseError::ExpectedOperator(n, token) => {
-                write!(f, "{}expected operator", fm(n, token))
-            }
             ParseError::ExpectedPrim(n, token) => write!(f, "{}expected primitive", fm(n, token)),
             ParseError::MissingValue(n, tt, token) => {
                 write!(f, "{}expected value for {:?}", fm(n, token), tt)
             }
-            ParseError::NotConvertible(n, from, to, token) => write!(
-                f,
-                "{}couldn't convert value from {:?} to {}",
-                fm(n, token),
-                from,
-                to
-            ),
+            ParseError::NumberTooLarge(n, token) => write!(f, "{}number too large", fm(n, token)),
         }
     }
 }
@@ -188,7 +486,7 @@ macro_rules! expect_id_next {
         if id_tok.token_type != TT::Id {
             return Err(ParseError::ExpectedToken($t, TT::Id, $self[*$pos].clone()));
         }
-        let Some(id) = id_tok.value.as_ref() else {
+        let Some(id) = id_tok.value.as_deref() else {
             return Err(ParseError::MissingValue($t, TT::Id, $self[*$pos].clone()));
         };
         *$pos += 1;
@@ -206,56 +504,168 @@ macro_rules! consume_next_tok {
 }
 macro_rules! cur_tok {
     ( $self:ident, $pos:ident ) => {{
-        $self.get(*$pos).ok_or(ParseError::NoTokensLeft)?
+        $self.get(*$pos).ok_or_else(|| {
+            ParseError::NoTokensLeft($self.last().map(|t| t.span.after()).unwrap_or_default())
+        })?
     }};
 }
 
+/// Span of the tokens from `start` up to `end` (exclusive)
+fn span_of(tokens: &[Token], start: usize, end: usize) -> Span {
+    let first = tokens.get(start).map(|t| t.span).unwrap_or_default();
+    let last = tokens.get(end.wrapping_sub(1)).map_or(first, |t| t.span);
+    first.to(last)
+}
+
+/// Index of the `;` ending the statement which continues at `start`, nested blocks and
+/// parentheses are skipped
+fn stmt_end(tokens: &[Token], start: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, tok) in tokens.iter().enumerate().skip(start) {
+        match tok.token_type {
+            TT::LBrace | TT::LParen => depth += 1,
+            TT::RBrace | TT::RParen if depth == 0 => return None,
+            TT::RBrace | TT::RParen => depth -= 1,
+            TT::Semicolon if depth == 0 => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Value of an integer literal like `-1_000`, `0xff` or `0b101`, `None` if it's too large
+fn parse_int(num: &str) -> Option<i128> {
+    let (sign, num) = match num.strip_prefix('-') {
+        Some(num) => (-1, num),
+        None => (1, num),
+    };
+    let num = num.replace('_', "");
+    let value = if let Some(hex) = num.strip_prefix("0x") {
+        i128::from_str_radix(hex, 16)
+    } else if let Some(bin) = num.strip_prefix("0b") {
+        i128::from_str_radix(bin, 2)
+    } else {
+        num.parse()
+    };
+    value.ok().map(|v| sign * v)
+}
+
+/// Skips the statement starting at `start` after it failed to parse: up to and including the
+/// next `;`, or up to the `}` closing the surrounding block. Nested blocks are skipped whole.
+fn synchronize(tokens: &[Token], pos: &mut usize, start: usize) {
+    *pos = start;
+    let mut depth = 0usize;
+    while let Some(tok) = tokens.get(*pos) {
+        match tok.token_type {
+            TT::LBrace => depth += 1,
+            // unless the statement itself is a stray `}` at the top level
+            TT::RBrace if depth == 0 && *pos > start => break,
+            TT::RBrace => depth = depth.saturating_sub(1),
+            TT::Semicolon if depth == 0 => {
+                *pos += 1;
+                break;
+            }
+            _ => (),
+        }
+        *pos += 1;
+    }
+}
+
 pub struct Parser<'a> {
     tokens: &'a Tokens<'a>,
+    max_errors: usize,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(tokens: &'a Tokens<'a>) -> Self {
-        Self { tokens }
+        Self {
+            tokens,
+            max_errors: usize::MAX,
+        }
+    }
+    /// Report at most `max_errors` errors
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors.max(1);
+        self
     }
-    pub fn parse(&mut self) -> Result<Program<'a>, ParseError> {
+    pub fn parse(&mut self) -> Result<Program<'a>, Vec<ParseError<'a>>> {
         let mut ast = vec![];
+        let mut errs = vec![];
         let mut pos: usize = 0;
-        while pos < self.tokens.len() {
-            let stmt = Stmt::parse(self.tokens, &mut pos)?;
-            ast.push(stmt);
+        while pos < self.tokens.len() && errs.len() < self.max_errors {
+            let start = pos;
+            match Stmt::parse(self.tokens, &mut pos, &mut errs) {
+                Ok(stmt) => ast.push(stmt),
+                Err(err) => {
+                    errs.push(err);
+                    synchronize(self.tokens, &mut pos, start);
+                }
+            }
+        }
+        if errs.is_empty() {
+            Ok(ast)
+        } else {
+            errs.truncate(self.max_errors);
+            Err(errs)
         }
-        Ok(ast)
     }
 }
 
 pub trait Parseable<'a> {
-    fn parse(tokens: &'a [Token<'a>], pos: &mut usize) -> Result<Self, ParseError<'a>>
+    /// Statements that fail to parse inside a block are recorded in `errs` and skipped, so a
+    /// single run reports as many errors as possible
+    fn parse(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+    ) -> Result<Self, ParseError<'a>>
     where
         Self: Sized;
 }
 
 impl<'a> Parseable<'a> for Stmt<'a> {
-    fn parse(tokens: &'a [Token<'a>], pos: &mut usize) -> Result<Self, ParseError<'a>> {
+    fn parse(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+    ) -> Result<Self, ParseError<'a>> {
         let tok = cur_tok!(tokens, pos);
         let ret = match tok.token_type {
-            TT::Funktion => Ok(Stmt::FunAss(FunAss::parse(tokens, pos)?)),
-            TT::Dä => Ok(Stmt::VarAss(VarAss::parse(tokens, pos)?)),
-            TT::Gib => Ok(Stmt::Ret(Ret::parse(tokens, pos)?)),
-            TT::Tuen | TT::LBrace => Ok(Stmt::StEx(StEx::parse(tokens, pos)?)),
+            TT::Funktion => Ok(Stmt::FunAss(FunAss::parse(tokens, pos, errs)?)),
+            TT::Dä => Ok(Stmt::VarAss(VarAss::parse(tokens, pos, errs)?)),
+            TT::Id => Ok(Stmt::Reass(Reass::parse(tokens, pos, errs)?)),
+            TT::Gib => Ok(Stmt::Ret(Ret::parse(tokens, pos, errs)?)),
+            TT::Wenn => Ok(Stmt::If(If::parse(tokens, pos, errs)?)),
+            TT::Solang => Ok(Stmt::While(While::parse(tokens, pos, errs)?)),
+            TT::Für => Ok(Stmt::For(Box::new(For::parse(tokens, pos, errs)?))),
+            TT::HörUf => {
+                *pos += 1;
+                Ok(Stmt::Break(tok.span))
+            }
+            TT::MachWiiter => {
+                *pos += 1;
+                Ok(Stmt::Continue(tok.span))
+            }
+            TT::Tuen | TT::LBrace => Ok(Stmt::StEx(StEx::parse(tokens, pos, errs)?)),
             _ => Err(ParseError::UnexpectedToken(
                 "Stmt".to_string(),
                 tokens[*pos].clone(),
             )),
         };
 
+        let stmt = ret?;
         consume_next_tok!("Stmt".to_string(), tokens, pos, TT::Semicolon);
-        ret
+        Ok(stmt)
     }
 }
 
 impl<'a> Parseable<'a> for FunAss<'a> {
-    fn parse(tokens: &'a [Token<'a>], pos: &mut usize) -> Result<Self, ParseError<'a>> {
+    fn parse(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+    ) -> Result<Self, ParseError<'a>> {
+        let start = *pos;
         *pos += 1;
         let id = expect_id_next!("FunAss".to_string(), tokens, pos);
 
@@ -266,9 +676,15 @@ impl<'a> Parseable<'a> for FunAss<'a> {
             loop {
                 let type_tok = cur_tok!(tokens, pos);
                 if let Some(pt) = PrimType::from_tt(type_tok.token_type) {
+                    let arg_start = *pos;
                     *pos += 1;
                     let arg_id = expect_id_next!("FunAss".to_string(), tokens, pos);
-                    args.push(Arg { pt, id: arg_id });
+                    args.push(Arg {
+                        pt,
+                        id: arg_id,
+                        decl: Cell::new(None),
+                        span: span_of(tokens, arg_start, *pos),
+                    });
                 } else {
                     return Err(ParseError::ExpectedType(
                         "FunAss".to_string(),
@@ -298,23 +714,45 @@ impl<'a> Parseable<'a> for FunAss<'a> {
                 ));
             }
         }
-        let body = Block::parse(tokens, pos)?;
+        let body = Block::parse(tokens, pos, errs)?;
 
         Ok(FunAss {
             id,
             body,
             ret,
             args,
+            decl: Cell::new(None),
+            span: span_of(tokens, start, *pos),
         })
     }
 }
 
 impl<'a> Parseable<'a> for VarAss<'a> {
-    fn parse(tokens: &'a [Token<'a>], pos: &mut usize) -> Result<Self, ParseError<'a>> {
+    fn parse(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+    ) -> Result<Self, ParseError<'a>> {
+        let start = *pos;
         *pos += 1;
+        let mutable = cur_tok!(tokens, pos).token_type == TT::Veränderlich;
+        if mutable {
+            *pos += 1;
+        }
         let id = expect_id_next!("VarAss".to_string(), tokens, pos);
         consume_next_tok!("VarAss".to_string(), tokens, pos, TT::Isch);
-        let value = Expr::parse(tokens, pos)?;
+        // a trailing `als <Type>` declares the type of the variable instead of converting the
+        // last operand, `(... als <Type>)` converts it
+        let value = match stmt_end(tokens, *pos) {
+            Some(end)
+                if end > *pos + 2
+                    && tokens[end - 2].token_type == TT::Als
+                    && PrimType::from_tt(tokens[end - 1].token_type).is_some() =>
+            {
+                Expr::parse(&tokens[..end - 2], pos, errs)?
+            }
+            _ => Expr::parse(tokens, pos, errs)?,
+        };
         let mut pt = None;
         if cur_tok!(tokens, pos).token_type == TT::Als {
             *pos += 1;
@@ -327,106 +765,273 @@ impl<'a> Parseable<'a> for VarAss<'a> {
                 ));
             }
         }
-        Ok(VarAss { id, value, pt })
+        Ok(VarAss {
+            id,
+            value,
+            pt,
+            mutable,
+            ty: Cell::new(None),
+            decl: Cell::new(None),
+            span: span_of(tokens, start, *pos),
+        })
     }
 }
-impl<'a> Parseable<'a> for Bin<'a> {
-    fn parse(tokens: &'a [Token<'a>], pos: &mut usize) -> Result<Self, ParseError<'a>> {
-        //TODO: operator precedence
-        let lhs = Box::new(match cur_tok!(tokens, pos).token_type {
-            TT::Tuen | TT::LBrace => Expr::StEx(StEx::parse(tokens, pos)?),
-            _ => Expr::Prim(Prim::parse(tokens, pos)?),
-        });
-        let op = BinOperator::from_tt(cur_tok!(tokens, pos).token_type).ok_or(
-            ParseError::ExpectedOperator("Bin".to_string(), tokens[*pos].clone()),
-        )?;
-        *pos += 1;
-        let rhs = Box::new(Expr::parse(tokens, pos)?);
-        Ok(Self { lhs, rhs, op })
+impl<'a> Parseable<'a> for Reass<'a> {
+    fn parse(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+    ) -> Result<Self, ParseError<'a>> {
+        let start = *pos;
+        let id = expect_id_next!("Reass".to_string(), tokens, pos);
+        consume_next_tok!("Reass".to_string(), tokens, pos, TT::Wird);
+        let value = Expr::parse(tokens, pos, errs)?;
+        Ok(Reass {
+            id,
+            value,
+            decl: Cell::new(None),
+            span: span_of(tokens, start, *pos),
+        })
     }
 }
 impl<'a> Parseable<'a> for Expr<'a> {
-    fn parse(tokens: &'a [Token<'a>], pos: &mut usize) -> Result<Self, ParseError<'a>> {
-        //FIXME: wonky
-        let prev = *pos;
-        match cur_tok!(tokens, pos).token_type {
-            TT::Tuen | TT::LBrace => Ok(Expr::StEx(StEx::parse(tokens, pos)?)),
-            _ if Bin::parse(tokens, pos).is_ok() => {
-                *pos = prev;
-                Ok(Expr::Bin(Bin::parse(tokens, pos)?))
+    fn parse(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+    ) -> Result<Self, ParseError<'a>> {
+        Expr::parse_with(tokens, pos, errs, false)
+    }
+}
+impl<'a> Expr<'a> {
+    fn new(kind: ExprKind<'a>, span: Span) -> Self {
+        Self {
+            kind,
+            span,
+            ty: Cell::new(None),
+        }
+    }
+    /// Parses the condition or bound in front of a body block, calls in it don't take a block as
+    /// argument so `wenn tuen f mit { ... }` doesn't swallow the body
+    fn parse_head(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+    ) -> Result<Self, ParseError<'a>> {
+        Expr::parse_with(tokens, pos, errs, true)
+    }
+    fn parse_with(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+        head: bool,
+    ) -> Result<Self, ParseError<'a>> {
+        let then = Expr::parse_prec(tokens, pos, 0, errs, head)?;
+        if tokens.get(*pos).map(|t| t.token_type) != Some(TT::Wenn) {
+            return Ok(then);
+        }
+        *pos += 1;
+        let cond = Expr::parse_prec(tokens, pos, 0, errs, head)?;
+        consume_next_tok!("Cond".to_string(), tokens, pos, TT::Suscht);
+        let els = Expr::parse_with(tokens, pos, errs, head)?;
+        let span = then.span.to(els.span);
+        Ok(Expr::new(
+            ExprKind::Cond(Cond {
+                cond: Box::new(cond),
+                then: Box::new(then),
+                els: Box::new(els),
+            }),
+            span,
+        ))
+    }
+    /// Precedence climbing: parses an expression whose binary operators all bind at least as
+    /// tight as `min_prec`.
+    fn parse_prec(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        min_prec: u8,
+        errs: &mut Vec<ParseError<'a>>,
+        head: bool,
+    ) -> Result<Self, ParseError<'a>> {
+        let mut lhs = Expr::parse_atom(tokens, pos, errs, head)?;
+        while let Some(op) = tokens
+            .get(*pos)
+            .and_then(|t| BinOperator::from_tt(t.token_type))
+        {
+            let prec = op.precedence();
+            if prec < min_prec {
+                break;
             }
-            _ => {
-                *pos = prev;
-                Ok(Expr::Prim(Prim::parse(tokens, pos)?))
+            *pos += 1;
+            let next_prec = if op.is_right_assoc() { prec } else { prec + 1 };
+            let rhs = Expr::parse_prec(tokens, pos, next_prec, errs, head)?;
+            let span = lhs.span.to(rhs.span);
+            lhs = Expr::new(
+                ExprKind::Bin(Bin {
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                    op,
+                }),
+                span,
+            );
+        }
+        Ok(lhs)
+    }
+    fn parse_atom(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+        head: bool,
+    ) -> Result<Self, ParseError<'a>> {
+        let start = *pos;
+        if let Some(op) = UnOperator::from_tt(cur_tok!(tokens, pos).token_type) {
+            *pos += 1;
+            let expr = Expr::parse_prec(tokens, pos, op.operand_precedence(), errs, head)?;
+            let span = span_of(tokens, start, start + 1).to(expr.span);
+            return Ok(Expr::new(
+                ExprKind::Un(Un {
+                    expr: Box::new(expr),
+                    op,
+                }),
+                span,
+            ));
+        }
+        let kind = match cur_tok!(tokens, pos).token_type {
+            TT::Tuen => ExprKind::StEx(StEx::Call(Call::parse_with(tokens, pos, errs, head)?)),
+            TT::LBrace => ExprKind::StEx(StEx::parse(tokens, pos, errs)?),
+            TT::LParen => {
+                *pos += 1;
+                let expr = Expr::parse(tokens, pos, errs)?;
+                consume_next_tok!("Expr".to_string(), tokens, pos, TT::RParen);
+                expr.kind
             }
+            _ => ExprKind::Prim(Prim::parse(tokens, pos, errs)?),
+        };
+        let mut expr = Expr::new(kind, span_of(tokens, start, *pos));
+        while tokens.get(*pos).map(|t| t.token_type) == Some(TT::Als) {
+            *pos += 1;
+            let Some(pt) = PrimType::from_tt(cur_tok!(tokens, pos).token_type) else {
+                return Err(ParseError::ExpectedType(
+                    "Cast".to_string(),
+                    tokens[*pos].clone(),
+                ));
+            };
+            *pos += 1;
+            expr = Expr::new(
+                ExprKind::Cast(Cast {
+                    expr: Box::new(expr),
+                    pt,
+                }),
+                span_of(tokens, start, *pos),
+            );
         }
+        Ok(expr)
     }
 }
 impl<'a> Parseable<'a> for Block<'a> {
-    fn parse(tokens: &'a [Token<'a>], pos: &mut usize) -> Result<Self, ParseError<'a>> {
-        *pos += 1;
+    fn parse(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+    ) -> Result<Self, ParseError<'a>> {
+        let start = *pos;
+        consume_next_tok!("Block".to_string(), tokens, pos, TT::LBrace);
         let mut stmts = vec![];
         while cur_tok!(tokens, pos).token_type != TT::RBrace {
-            stmts.push(Stmt::parse(tokens, pos)?);
+            let stmt_start = *pos;
+            match Stmt::parse(tokens, pos, errs) {
+                Ok(stmt) => stmts.push(stmt),
+                // nothing left to recover with
+                Err(err @ ParseError::NoTokensLeft(_)) => return Err(err),
+                Err(err) => {
+                    errs.push(err);
+                    synchronize(tokens, pos, stmt_start);
+                }
+            }
         }
         consume_next_tok!("Block".to_string(), tokens, pos, TT::RBrace);
-        Ok(Block { stmts })
+        Ok(Block {
+            stmts,
+            span: span_of(tokens, start, *pos),
+        })
     }
 }
 impl<'a> Parseable<'a> for Prim<'a> {
-    fn parse(tokens: &'a [Token<'a>], pos: &mut usize) -> Result<Self, ParseError<'a>> {
+    fn parse(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        _errs: &mut Vec<ParseError<'a>>,
+    ) -> Result<Self, ParseError<'a>> {
         let tok = cur_tok!(tokens, pos);
         *pos += 1;
         match tok.token_type {
-            TT::Num => Ok(Prim::R8(
-                tok.value
-                    .ok_or(ParseError::MissingValue(
-                        "Prim".to_string(),
-                        TT::Str,
-                        tokens[*pos].clone(),
-                    ))?
-                    .parse()
-                    .map_err(|_| {
-                        ParseError::NotConvertible(
-                            "Prim".to_string(),
-                            TT::Str,
-                            PrimType::R8,
-                            tokens[*pos].clone(),
-                        )
-                    })?,
-            )),
+            TT::Int | TT::Float => {
+                let num = tok.value.as_deref().ok_or(ParseError::MissingValue(
+                    "Prim".to_string(),
+                    tok.token_type,
+                    tok.clone(),
+                ))?;
+                if tok.token_type == TT::Float {
+                    // the lexer only lets through valid floats, too large ones become infinite
+                    return Ok(Prim::Float(
+                        num.replace('_', "").parse().unwrap_or(f64::INFINITY),
+                    ));
+                }
+                parse_int(num)
+                    .map(Prim::Int)
+                    .ok_or(ParseError::NumberTooLarge("Prim".to_string(), tok.clone()))
+            }
             TT::Wahr => Ok(Prim::Bool(true)),
             TT::Falsch => Ok(Prim::Bool(false)),
-            TT::Str => Ok(Prim::Str(tok.value.ok_or(ParseError::MissingValue(
-                "Prim".to_string(),
-                TT::Str,
-                tokens[*pos].clone(),
-            ))?)),
-            TT::Id => Ok(Prim::Id(tok.value.ok_or(ParseError::MissingValue(
-                "Prim".to_string(),
-                TT::Id,
-                tokens[*pos].clone(),
-            ))?)),
-            _ => Err(ParseError::ExpectedPrim(
-                "Prim".to_string(),
-                tokens[*pos].clone(),
+            TT::Str => Ok(Prim::Str(tok.value.as_deref().ok_or(
+                ParseError::MissingValue("Prim".to_string(), TT::Str, tok.clone()),
+            )?)),
+            TT::Id => Ok(Prim::Id(
+                tok.value.as_deref().ok_or(ParseError::MissingValue(
+                    "Prim".to_string(),
+                    TT::Id,
+                    tok.clone(),
+                ))?,
+                Cell::new(None),
             )),
+            _ => Err(ParseError::ExpectedPrim("Prim".to_string(), tok.clone())),
         }
     }
 }
 impl<'a> Parseable<'a> for Call<'a> {
-    fn parse(tokens: &'a [Token<'a>], pos: &mut usize) -> Result<Self, ParseError<'a>> {
+    fn parse(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+    ) -> Result<Self, ParseError<'a>> {
+        Call::parse_with(tokens, pos, errs, false)
+    }
+}
+impl<'a> Call<'a> {
+    /// In a `head`, see [Expr::parse_head], a `{` ends the arguments
+    fn parse_with(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+        head: bool,
+    ) -> Result<Self, ParseError<'a>> {
+        let start = *pos;
         *pos += 1;
         let id = expect_id_next!("Call".to_string(), tokens, pos);
         consume_next_tok!("Call".to_string(), tokens, pos, TT::Mit);
         let mut args = vec![];
         let mut prev = *pos;
         loop {
-            if let Ok(a) = Expr::parse(tokens, pos) {
+            if head && tokens.get(*pos).map(|t| t.token_type) == Some(TT::LBrace) {
+                break;
+            }
+            let prev_errs = errs.len();
+            if let Ok(a) = Expr::parse_with(tokens, pos, errs, head) {
                 args.push(a);
                 prev = *pos;
             } else {
                 *pos = prev;
+                errs.truncate(prev_errs);
                 break;
             }
             if cur_tok!(tokens, pos).token_type != TT::Comma {
@@ -434,22 +1039,112 @@ impl<'a> Parseable<'a> for Call<'a> {
             }
             *pos += 1;
         }
-        Ok(Call { id, args })
+        Ok(Call {
+            id,
+            args,
+            decl: Cell::new(None),
+            span: span_of(tokens, start, *pos),
+        })
+    }
+}
+impl<'a> Parseable<'a> for If<'a> {
+    fn parse(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+    ) -> Result<Self, ParseError<'a>> {
+        let start = *pos;
+        *pos += 1;
+        let cond = Expr::parse_head(tokens, pos, errs)?;
+        let then = Block::parse(tokens, pos, errs)?;
+        let mut els = None;
+        if tokens.get(*pos).map(|t| t.token_type) == Some(TT::Suscht) {
+            *pos += 1;
+            els = Some(match cur_tok!(tokens, pos).token_type {
+                TT::Wenn => Else::If(Box::new(If::parse(tokens, pos, errs)?)),
+                _ => Else::Block(Block::parse(tokens, pos, errs)?),
+            });
+        }
+        Ok(If {
+            cond,
+            then,
+            els,
+            span: span_of(tokens, start, *pos),
+        })
+    }
+}
+impl<'a> Parseable<'a> for While<'a> {
+    fn parse(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+    ) -> Result<Self, ParseError<'a>> {
+        let start = *pos;
+        *pos += 1;
+        let cond = Expr::parse_head(tokens, pos, errs)?;
+        let body = Block::parse(tokens, pos, errs)?;
+        Ok(While {
+            cond,
+            body,
+            span: span_of(tokens, start, *pos),
+        })
+    }
+}
+impl<'a> Parseable<'a> for For<'a> {
+    fn parse(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+    ) -> Result<Self, ParseError<'a>> {
+        let start = *pos;
+        *pos += 1;
+        let id = expect_id_next!("For".to_string(), tokens, pos);
+        consume_next_tok!("For".to_string(), tokens, pos, TT::Vo);
+        let from = Expr::parse(tokens, pos, errs)?;
+        consume_next_tok!("For".to_string(), tokens, pos, TT::Bis);
+        let to = Expr::parse_head(tokens, pos, errs)?;
+        let mut step = None;
+        if cur_tok!(tokens, pos).token_type == TT::Schritt {
+            *pos += 1;
+            step = Some(Expr::parse_head(tokens, pos, errs)?);
+        }
+        let body = Block::parse(tokens, pos, errs)?;
+        Ok(For {
+            id,
+            from,
+            to,
+            step,
+            body,
+            ty: Cell::new(None),
+            decl: Cell::new(None),
+            span: span_of(tokens, start, *pos),
+        })
     }
 }
 impl<'a> Parseable<'a> for Ret<'a> {
-    fn parse(tokens: &'a [Token<'a>], pos: &mut usize) -> Result<Self, ParseError<'a>> {
+    fn parse(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+    ) -> Result<Self, ParseError<'a>> {
+        let start = *pos;
         *pos += 1;
+        let expr = Expr::parse(tokens, pos, errs)?;
         Ok(Ret {
-            expr: Expr::parse(tokens, pos)?,
+            expr,
+            span: span_of(tokens, start, *pos),
         })
     }
 }
 impl<'a> Parseable<'a> for StEx<'a> {
-    fn parse(tokens: &'a [Token<'a>], pos: &mut usize) -> Result<Self, ParseError<'a>> {
+    fn parse(
+        tokens: &'a [Token<'a>],
+        pos: &mut usize,
+        errs: &mut Vec<ParseError<'a>>,
+    ) -> Result<Self, ParseError<'a>> {
         match cur_tok!(tokens, pos).token_type {
-            TT::Tuen => Ok(StEx::Call(Call::parse(tokens, pos)?)),
-            TT::LBrace => Ok(StEx::Block(Block::parse(tokens, pos)?)),
+            TT::Tuen => Ok(StEx::Call(Call::parse(tokens, pos, errs)?)),
+            TT::LBrace => Ok(StEx::Block(Block::parse(tokens, pos, errs)?)),
             _ => Err(ParseError::UnexpectedToken(
                 "StEx".to_string(),
                 tokens[*pos].clone(),
@@ -1,19 +1,24 @@
 use std::{collections::HashMap, fmt::Display};
 
+use crate::diag::Diagnostic;
+use crate::lexer::Span;
 use crate::parser::{
-    Bin, Block, Call, Expr, FunAss, Prim, PrimType, Program, StEx, Stmt, VarAss,
+    Arg, Bin, BinOperator, Block, Call, Cast, Cond, DeclId, Else, Expr, ExprKind, For, FunAss, If,
+    Prim, PrimType, Program, Reass, Ret, StEx, Stmt, Un, UnOperator, VarAss, While,
 };
 
 // TODO: check
-// [ ] arg types match fn definition
-// [ ] arg count matches fn definition
-// [ ] return type matches fn definition
-// [ ] value type matches variable type on assign
-// [ ] if/while boolean predicate
+// [x] arg types match fn definition
+// [x] arg count matches fn definition
+// [x] return type matches fn definition
+// [x] value type matches variable type on assign
+// [x] if/while boolean predicate
+// [x] break/continue only inside loops
 // [x] no multiple declarations with same id
 // [ ] no id is reserved keyword
 // [ ] only one main method
-// [ ] bin/un operators with correct types
+// [x] bin operators with correct types
+// [x] un operators with correct types
 // [ ] uninitialized vars can't be accessed
 // [ ] division by zero
 // [ ] null-dereferencing
@@ -21,38 +26,320 @@ use crate::parser::{
 
 const BUILTINS: [&str; 2] = ["schreie", "verlange"];
 
-type Scope<'a> = HashMap<&'a str, Option<&'a PrimType>>;
+#[derive(Debug, Clone, Copy)]
+pub enum Symbol<'a> {
+    Var {
+        pt: PrimType,
+        mutable: bool,
+    },
+    Fun {
+        args: &'a [Arg<'a>],
+        ret: Option<PrimType>,
+    },
+    /// variable whose declaration has an error, so its type is unknown
+    Unknown,
+}
+
+impl<'a> Display for Symbol<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Symbol::Var { pt, .. } => write!(f, "{}", pt),
+            Symbol::Fun { .. } => write!(f, "funktion"),
+            Symbol::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// A variable, argument, loop variable or function declared in the program
+#[derive(Debug, Clone, Copy)]
+pub struct Decl<'a> {
+    pub id: &'a str,
+    pub symbol: Symbol<'a>,
+    pub span: Span,
+}
+
+type Scope<'a> = HashMap<&'a str, DeclId>;
+
+/// Program which passed semantic analysis. Every expression in it carries its type and every
+/// identifier is resolved to its declaration.
+#[derive(Debug)]
+pub struct TypedProgram<'a> {
+    ast: &'a Program<'a>,
+    decls: Vec<Decl<'a>>,
+}
+
+impl<'a> TypedProgram<'a> {
+    pub fn ast(&self) -> &'a Program<'a> {
+        self.ast
+    }
+    pub fn decl(&self, id: DeclId) -> &Decl<'a> {
+        &self.decls[id]
+    }
+}
+
+/// What a `gib` returns from
+#[derive(Debug, Clone, Copy)]
+enum RetTarget<'a> {
+    Fun(&'a str, Option<PrimType>),
+    /// Block used as an expression, its type is the type of its first `gib`
+    Block {
+        hint: Option<PrimType>,
+        pt: Option<PrimType>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct SemAnError<'a> {
+    pub kind: SemAnErrorKind<'a>,
+    pub span: Span,
+}
 
 #[derive(Debug, Clone)]
-pub enum SemAnError<'a> {
-    /* TODO:
-    pos: usize,
-    row: usize,
-    col: usize,
-    */
+pub enum SemAnErrorKind<'a> {
     TokenNotDefined(&'a str),
     FunctionNotDefined(&'a str),
     SameFunctionArgs(&'a str, &'a str),
     ArgNotDefined(&'a str, &'a str),
     AssignTokenNotDefined(&'a str, &'a str),
+    CondNotBoolean(PrimType),
+    CondBranchMismatch(PrimType, PrimType),
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    ForBoundNotInteger(&'a str, PrimType),
+    ForBoundMismatch(&'a str, PrimType, PrimType),
+    ForStepNotPositive(&'a str),
+    AssignLoopVar(&'a str),
+    ReassNotDefined(&'a str),
+    ReassTypeMismatch(&'a str, PrimType, PrimType),
+    // id and the span of its declaration
+    AssignImmutable(&'a str, Span),
+    NotAVariable(&'a str, Span),
+    NotAFunction(&'a str, Span),
+    FunctionDeclaredTwice(&'a str, Span),
+    /// top level variable read by a call and the span of its declaration
+    ReadBeforeSet(&'a str, Span),
+    NoValue(&'a str),
+    BlockNoValue,
+    BlockTypeMismatch(PrimType, PrimType),
+    ArgCountMismatch(&'a str, usize, usize),
+    /// function, declared argument and type of the passed value
+    ArgTypeMismatch(&'a str, &'a Arg<'a>, PrimType),
+    RetTypeMismatch(&'a str, PrimType, PrimType),
+    RetInVoidFun(&'a str),
+    RetOutsideFun,
+    VarAssTypeMismatch(&'a str, PrimType, PrimType),
+    BinOperandMismatch(BinOperator, PrimType, PrimType),
+    BinOperandType(BinOperator, PrimType),
+    UnOperandType(UnOperator, PrimType),
+    LiteralOutOfRange(PrimType),
+    CastNotAllowed(PrimType, PrimType),
+    /// use of a variable of unknown type, its declaration's error is reported instead
+    FollowUp,
+}
+
+impl<'a> SemAnErrorKind<'a> {
+    fn at(self, span: Span) -> SemAnError<'a> {
+        SemAnError { kind: self, span }
+    }
 }
 
 impl<'a> Display for SemAnError<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Your code is semantically incorrect :) {}",
+            "Your code is semantically incorrect at {} :) {}",
+            self.span, self.kind
+        )
+    }
+}
+
+impl<'a> From<&SemAnError<'a>> for Diagnostic {
+    fn from(err: &SemAnError<'a>) -> Self {
+        let diag = Diagnostic::error(err.kind.to_string());
+        match err.kind {
+            SemAnErrorKind::TokenNotDefined(id)
+            | SemAnErrorKind::AssignTokenNotDefined(id, _)
+            | SemAnErrorKind::ArgNotDefined(_, id) => diag.primary(err.span, "not defined").help(
+                format!("declare it with `dä {} isch ...;` before using it", id),
+            ),
+            SemAnErrorKind::FunctionNotDefined(_) => diag.primary(err.span, "not defined"),
+            SemAnErrorKind::SameFunctionArgs(..) => diag.primary(err.span, "declared twice"),
+            SemAnErrorKind::BreakOutsideLoop | SemAnErrorKind::ContinueOutsideLoop => diag
+                .primary(err.span, "not inside a loop")
+                .note("`hör uf` and `mach wiiter` only work inside `solang` and `für` loops"),
+            SemAnErrorKind::ForStepNotPositive(_) => diag
+                .primary(err.span, "not greater than 0")
+                .note("`für` loops count upwards from `vo` to `bis`"),
+            SemAnErrorKind::AssignLoopVar(_) => diag
+                .primary(err.span, "assigned here")
+                .note("loop variables are set by their loop on every iteration"),
+            SemAnErrorKind::ReassNotDefined(id) => diag.primary(err.span, "not defined").help(
+                format!("declare it with `dä veränderlich {} isch ...;`", id),
+            ),
+            SemAnErrorKind::AssignImmutable(id, decl) => diag
+                .primary(err.span, "assigned here")
+                .secondary(decl, "declared immutable here")
+                .help(format!(
+                    "declare it with `dä veränderlich {} isch ...;`",
+                    id
+                )),
+            SemAnErrorKind::NotAVariable(_, decl) => diag
+                .primary(err.span, "used as a value")
+                .secondary(decl, "declared as a function here")
+                .help("call it with `tuen ... mit ...`"),
+            SemAnErrorKind::NotAFunction(_, decl) => diag
+                .primary(err.span, "called here")
+                .secondary(decl, "declared as a variable here"),
+            SemAnErrorKind::FunctionDeclaredTwice(_, decl) => diag
+                .primary(err.span, "declared again here")
+                .secondary(decl, "first declared here")
+                .note(
+                    "top level functions can be called before they're declared, so their names \
+                     must be unique",
+                ),
+            SemAnErrorKind::ReadBeforeSet(_, decl) => diag
+                .primary(err.span, "called here")
+                .secondary(decl, "set here")
+                .note("top level variables are set in the order they're written"),
+            SemAnErrorKind::ArgTypeMismatch(_, arg, _) => diag
+                .primary(err.span, format!("expected {}", arg.pt))
+                .secondary(arg.span, "argument declared here"),
+            SemAnErrorKind::RetInVoidFun(fun) => {
+                diag.primary(err.span, "returns a value").help(format!(
+                    "declare a return type with `funktion {} git <Typ> {{ ... }};`",
+                    fun
+                ))
+            }
+            SemAnErrorKind::RetOutsideFun => diag.primary(err.span, "not inside a function"),
+            SemAnErrorKind::BlockNoValue => diag.primary(err.span, "doesn't `gib` a value"),
+            // a trailing `als` declares the type, it doesn't convert
+            SemAnErrorKind::VarAssTypeMismatch(id, exp, got) if castable(got, exp) => diag
+                .primary(err.span, format!("expected {}", exp))
+                .help(format!(
+                    "convert the value with `dä {} isch (... als {});`",
+                    id, exp
+                )),
+            SemAnErrorKind::ReassTypeMismatch(_, exp, _)
+            | SemAnErrorKind::RetTypeMismatch(_, exp, _)
+            | SemAnErrorKind::VarAssTypeMismatch(_, exp, _)
+            | SemAnErrorKind::BlockTypeMismatch(exp, _) => {
+                diag.primary(err.span, format!("expected {}", exp))
+            }
+            SemAnErrorKind::CondNotBoolean(_) => diag.primary(err.span, "expected Wahrheit"),
+            SemAnErrorKind::BinOperandType(_, pt) | SemAnErrorKind::UnOperandType(_, pt) => {
+                diag.primary(err.span, format!("this is {}", pt))
+            }
+            SemAnErrorKind::CastNotAllowed(from, _) => {
+                diag.primary(err.span, format!("this is {}", from)).note(
+                    "`als` converts between number types, Wahrheit to numbers and numbers or \
+                     Wahrheit to Zeiche",
+                )
+            }
+            SemAnErrorKind::LiteralOutOfRange(pt) => {
+                let diag = diag.primary(err.span, format!("doesn't fit {}", pt));
+                match (pt.int_range(), pt.float_max()) {
+                    (Some((min, max)), _) => {
+                        diag.note(format!("{} goes from {} to {}", pt, min, max))
+                    }
+                    (_, Some(max)) => diag.note(format!("{} goes up to {:e}", pt, max)),
+                    _ => diag,
+                }
+            }
+            _ => diag.primary(err.span, ""),
+        }
+    }
+}
+
+impl<'a> Display for SemAnErrorKind<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
             match self {
-                SemAnError::TokenNotDefined(id) => format!("Token `{}` not defined", id),
-                SemAnError::AssignTokenNotDefined(id, ass) =>
+                SemAnErrorKind::TokenNotDefined(id) => format!("Token `{}` not defined", id),
+                SemAnErrorKind::AssignTokenNotDefined(id, ass) =>
                     format!("Token `{}` not defined when assigning to `{}`", id, ass),
-                SemAnError::FunctionNotDefined(id) => format!("Function `{}` not defined", id),
-                SemAnError::SameFunctionArgs(id, fun) => format!(
+                SemAnErrorKind::FunctionNotDefined(id) => format!("Function `{}` not defined", id),
+                SemAnErrorKind::SameFunctionArgs(id, fun) => format!(
                     "Duplicate id's `{}` passed as function args to `{}`",
                     id, fun
                 ),
-                SemAnError::ArgNotDefined(id, fun) =>
+                SemAnErrorKind::ArgNotDefined(id, fun) =>
                     format!("Argument `{}` for function `{}` not defined", fun, id),
+                SemAnErrorKind::CondNotBoolean(pt) =>
+                    format!("Condition after `wenn` must be Wahrheit, got {}", pt),
+                SemAnErrorKind::CondBranchMismatch(then, els) => format!(
+                    "Branches of `wenn ... suscht` have different types {} and {}",
+                    then, els
+                ),
+                SemAnErrorKind::BreakOutsideLoop => "`hör uf` outside of a loop".to_string(),
+                SemAnErrorKind::ContinueOutsideLoop =>
+                    "`mach wiiter` outside of a loop".to_string(),
+                SemAnErrorKind::ForBoundNotInteger(id, pt) => format!(
+                    "Bounds of loop variable `{}` must be integers, got {}",
+                    id, pt
+                ),
+                SemAnErrorKind::ForBoundMismatch(id, exp, got) => format!(
+                    "Bounds of loop variable `{}` have different types {} and {}",
+                    id, exp, got
+                ),
+                SemAnErrorKind::ForStepNotPositive(id) =>
+                    format!("Step of loop variable `{}` must be greater than 0", id),
+                SemAnErrorKind::AssignLoopVar(id) =>
+                    format!("Can't assign to loop variable `{}` inside its loop", id),
+                SemAnErrorKind::ReassNotDefined(id) =>
+                    format!("Can't assign to `{}` as it isn't defined", id),
+                SemAnErrorKind::ReassTypeMismatch(id, exp, got) => format!(
+                    "Can't assign value of type {} to `{}` of type {}",
+                    got, id, exp
+                ),
+                SemAnErrorKind::AssignImmutable(id, _) => format!(
+                    "Can't assign to `{}` as it isn't declared with `dä veränderlich`",
+                    id
+                ),
+                SemAnErrorKind::NotAVariable(id, _) =>
+                    format!("Function `{}` can't be used as a value", id),
+                SemAnErrorKind::NotAFunction(id, _) =>
+                    format!("`{}` is a variable, not a function", id),
+                SemAnErrorKind::FunctionDeclaredTwice(id, _) =>
+                    format!("Function `{}` is already declared", id),
+                SemAnErrorKind::ReadBeforeSet(id, _) =>
+                    format!("Call reads `{}` before it's set", id),
+                SemAnErrorKind::NoValue(id) => format!("Function `{}` doesn't `git` a value", id),
+                SemAnErrorKind::BlockNoValue =>
+                    "Block used as a value doesn't `gib` one".to_string(),
+                SemAnErrorKind::BlockTypeMismatch(exp, got) =>
+                    format!("Block gives values of different types {} and {}", exp, got),
+                SemAnErrorKind::ArgCountMismatch(fun, exp, got) =>
+                    format!("Function `{}` takes {} arguments but got {}", fun, exp, got),
+                SemAnErrorKind::ArgTypeMismatch(fun, arg, got) => format!(
+                    "Argument `{}` of function `{}` is {} but got {}",
+                    arg.id, fun, arg.pt, got
+                ),
+                SemAnErrorKind::RetTypeMismatch(fun, exp, got) =>
+                    format!("Function `{}` returns {} but `gib` got {}", fun, exp, got),
+                SemAnErrorKind::RetInVoidFun(fun) => format!(
+                    "Function `{}` has no `git` type, so it can't `gib` a value",
+                    fun
+                ),
+                SemAnErrorKind::RetOutsideFun => "`gib` outside of a function".to_string(),
+                SemAnErrorKind::VarAssTypeMismatch(id, exp, got) => format!(
+                    "Can't assign value of type {} to `{}` declared `als` {}",
+                    got, id, exp
+                ),
+                SemAnErrorKind::BinOperandMismatch(op, lhs, rhs) => format!(
+                    "Operands of `{}` have different types {} and {}",
+                    op, lhs, rhs
+                ),
+                SemAnErrorKind::BinOperandType(op, pt) =>
+                    format!("Operator `{}` can't be used with {}", op, pt),
+                SemAnErrorKind::UnOperandType(op, pt) =>
+                    format!("Operator `{}` can't be used with {}", op, pt),
+                SemAnErrorKind::CastNotAllowed(from, to) =>
+                    format!("Can't convert {} to {} with `als`", from, to),
+                SemAnErrorKind::LiteralOutOfRange(pt) =>
+                    format!("Number is out of range for {}", pt),
+                SemAnErrorKind::FollowUp => "Follow-up error".to_string(),
             }
         )
     }
@@ -62,22 +349,51 @@ pub trait Analyzable<'a> {
     fn analyze(&'a self, ctx: &mut SemanticAnalyzer<'a>) -> Result<(), SemAnError<'a>>;
 }
 
+pub trait Typeable<'a> {
+    /// Checks the expression and computes its type. `hint` is the type the surrounding code
+    /// expects, number literals take it on if it's numeric.
+    fn type_check(
+        &'a self,
+        ctx: &mut SemanticAnalyzer<'a>,
+        hint: Option<PrimType>,
+    ) -> Result<PrimType, SemAnError<'a>>;
+}
+
 impl<'a> Analyzable<'a> for FunAss<'a> {
     fn analyze(&'a self, ctx: &mut SemanticAnalyzer<'a>) -> Result<(), SemAnError<'a>> {
-        ctx.add_symbol(self.id, self.ret.as_ref());
+        // top level functions are already declared by the hoisting pass
+        let top_level = ctx.scope_stack.len() == 1;
+        if top_level {
+            ctx.cur_fun = self.decl.get();
+        } else {
+            ctx.declare_fun(self);
+        }
         for (i, x) in self.args.iter().enumerate() {
             for y in self.args.iter().skip(i + 1) {
                 if x.id == y.id {
                     println!("{} {} {}", x.id, y.id, i);
-                    return Err(SemAnError::SameFunctionArgs(x.id, self.id));
+                    return Err(SemAnErrorKind::SameFunctionArgs(x.id, self.id).at(y.span));
                 }
             }
         }
+        ctx.scope_stack.push(HashMap::new());
         for arg in self.args.iter() {
-            ctx.add_symbol(arg.id, Some(&arg.pt));
+            arg.decl
+                .set(Some(ctx.add_var(arg.id, arg.pt, false, arg.span)));
         }
-        self.body.analyze(ctx)?;
-        Ok(())
+        ctx.ret_stack.push(RetTarget::Fun(self.id, self.ret));
+        // loops don't reach into nested functions
+        let loop_depth = std::mem::take(&mut ctx.loop_depth);
+        let loop_vars = std::mem::take(&mut ctx.loop_vars);
+        let res = self.body.analyze(ctx);
+        ctx.loop_depth = loop_depth;
+        ctx.loop_vars = loop_vars;
+        ctx.ret_stack.pop();
+        ctx.scope_stack.pop();
+        if top_level {
+            ctx.cur_fun = None;
+        }
+        res
     }
 }
 
@@ -86,41 +402,50 @@ impl<'a> Analyzable<'a> for Stmt<'a> {
         match self {
             Stmt::FunAss(fun_ass) => fun_ass.analyze(ctx)?,
             Stmt::VarAss(var_ass) => var_ass.analyze(ctx)?,
+            Stmt::Reass(reass) => reass.analyze(ctx)?,
             Stmt::StEx(st_ex) => st_ex.analyze(ctx)?,
-            Stmt::Ret(ret) => ret.expr.analyze(ctx)?,
-        }
-        Ok(())
-    }
-}
-
-impl<'a> Analyzable<'a> for Expr<'a> {
-    fn analyze(&'a self, ctx: &mut SemanticAnalyzer<'a>) -> Result<(), SemAnError<'a>> {
-        match self {
-            Expr::StEx(st_ex) => st_ex.analyze(ctx)?,
-            Expr::Prim(prim) => prim.analyze(ctx)?,
-            Expr::Bin(bin) => bin.analyze(ctx)?,
+            Stmt::Ret(ret) => ret.analyze(ctx)?,
+            Stmt::If(if_stmt) => if_stmt.analyze(ctx)?,
+            Stmt::While(while_stmt) => while_stmt.analyze(ctx)?,
+            Stmt::For(for_stmt) => for_stmt.analyze(ctx)?,
+            Stmt::Break(span) if ctx.loop_depth == 0 => {
+                return Err(SemAnErrorKind::BreakOutsideLoop.at(*span))
+            }
+            Stmt::Continue(span) if ctx.loop_depth == 0 => {
+                return Err(SemAnErrorKind::ContinueOutsideLoop.at(*span))
+            }
+            Stmt::Break(_) | Stmt::Continue(_) => (),
         }
         Ok(())
     }
 }
 
-impl<'a> Analyzable<'a> for Prim<'a> {
-    fn analyze(&'a self, ctx: &mut SemanticAnalyzer<'a>) -> Result<(), SemAnError<'a>> {
-        if let Prim::Id(id) = self {
-            if !ctx.has_symbol(id) {
-                return Err(SemAnError::TokenNotDefined(id));
-            }
-        }
-        Ok(())
+impl<'a> Typeable<'a> for Expr<'a> {
+    fn type_check(
+        &'a self,
+        ctx: &mut SemanticAnalyzer<'a>,
+        hint: Option<PrimType>,
+    ) -> Result<PrimType, SemAnError<'a>> {
+        let pt = match &self.kind {
+            ExprKind::StEx(StEx::Call(call)) => ctx
+                .call_type(call)?
+                .ok_or(SemAnErrorKind::NoValue(call.id).at(call.span)),
+            ExprKind::StEx(StEx::Block(block)) => ctx.block_type(block, hint),
+            ExprKind::Prim(prim) => ctx.prim_type(prim, self.span, hint),
+            ExprKind::Un(un) => un.type_check(ctx, hint),
+            ExprKind::Bin(bin) => bin.type_check(ctx, hint),
+            ExprKind::Cond(cond) => cond.type_check(ctx, hint),
+            ExprKind::Cast(cast) => cast.type_check(ctx, hint),
+        }?;
+        self.ty.set(Some(pt));
+        Ok(pt)
     }
 }
 
 impl<'a> Analyzable<'a> for Block<'a> {
     fn analyze(&'a self, ctx: &mut SemanticAnalyzer<'a>) -> Result<(), SemAnError<'a>> {
         ctx.scope_stack.push(HashMap::new());
-        for stmt in self.stmts.iter() {
-            stmt.analyze(ctx)?;
-        }
+        ctx.analyze_stmts(&self.stmts);
         ctx.scope_stack.pop();
         Ok(())
     }
@@ -128,21 +453,7 @@ impl<'a> Analyzable<'a> for Block<'a> {
 
 impl<'a> Analyzable<'a> for Call<'a> {
     fn analyze(&'a self, ctx: &mut SemanticAnalyzer<'a>) -> Result<(), SemAnError<'a>> {
-        if !ctx.has_symbol(self.id) && !BUILTINS.contains(&self.id) {
-            return Err(SemAnError::FunctionNotDefined(self.id));
-        }
-        for arg in self.args.iter() {
-            if let Err(e) = arg.analyze(ctx) {
-                return Err(SemAnError::ArgNotDefined(
-                    self.id,
-                    match e {
-                        SemAnError::TokenNotDefined(t) => t,
-                        SemAnError::FunctionNotDefined(t) => t,
-                        _ => "",
-                    },
-                ));
-            }
-        }
+        ctx.call_type(self)?;
         Ok(())
     }
 }
@@ -156,125 +467,631 @@ impl<'a> Analyzable<'a> for StEx<'a> {
         Ok(())
     }
 }
-impl<'a> Analyzable<'a> for Bin<'a> {
-    fn analyze(&'a self, ctx: &mut SemanticAnalyzer<'a>) -> Result<(), SemAnError<'a>> {
-        //TODO: check type and operator
-        self.lhs.analyze(ctx)?;
-        self.rhs.analyze(ctx)?;
-        /*
-        match self.op {
+
+impl<'a> Typeable<'a> for Bin<'a> {
+    fn type_check(
+        &'a self,
+        ctx: &mut SemanticAnalyzer<'a>,
+        hint: Option<PrimType>,
+    ) -> Result<PrimType, SemAnError<'a>> {
+        let (operand_ok, hint, res): (fn(&PrimType) -> bool, _, _) = match self.op {
             // Eq
-            BinOperator::Gliich | BinOperator::Ungliich => todo!(),
+            BinOperator::Gliich | BinOperator::Ungliich => {
+                (|_| true, None, Some(PrimType::Boolean))
+            }
             // Cmp
             BinOperator::GrösserGliich
             | BinOperator::Grösser
             | BinOperator::ChlinnerGliich
-            | BinOperator::Chlinner => todo!(),
+            | BinOperator::Chlinner => (PrimType::is_num, None, Some(PrimType::Boolean)),
             // Bool
-            BinOperator::Und | BinOperator::Oder => todo!(),
+            BinOperator::Und | BinOperator::Oder => (
+                |pt| *pt == PrimType::Boolean,
+                Some(PrimType::Boolean),
+                Some(PrimType::Boolean),
+            ),
             // Num
-            BinOperator::Rescht
-            | BinOperator::Hoch
+            BinOperator::Rescht => (PrimType::is_int, hint, None),
+            BinOperator::Hoch
             | BinOperator::Mal
             | BinOperator::Durch
             | BinOperator::Plus
-            | BinOperator::Minus => todo!(),
+            | BinOperator::Minus => (PrimType::is_num, hint, None),
         };
-*/
+        let (lhs, rhs) = ctx.type_check_pair(&self.lhs, &self.rhs, hint)?;
+        for (pt, operand) in [(lhs, &self.lhs), (rhs, &self.rhs)] {
+            if !operand_ok(&pt) {
+                return Err(SemAnErrorKind::BinOperandType(self.op, pt).at(operand.span));
+            }
+        }
+        if lhs != rhs {
+            let span = self.lhs.span.to(self.rhs.span);
+            return Err(SemAnErrorKind::BinOperandMismatch(self.op, lhs, rhs).at(span));
+        }
+        Ok(res.unwrap_or(lhs))
+    }
+}
+
+impl<'a> Typeable<'a> for Cond<'a> {
+    fn type_check(
+        &'a self,
+        ctx: &mut SemanticAnalyzer<'a>,
+        hint: Option<PrimType>,
+    ) -> Result<PrimType, SemAnError<'a>> {
+        ctx.check_predicate(&self.cond)?;
+        let (then, els) = ctx.type_check_pair(&self.then, &self.els, hint)?;
+        if then != els {
+            let span = self.then.span.to(self.els.span);
+            return Err(SemAnErrorKind::CondBranchMismatch(then, els).at(span));
+        }
+        Ok(then)
+    }
+}
+
+impl<'a> Analyzable<'a> for If<'a> {
+    fn analyze(&'a self, ctx: &mut SemanticAnalyzer<'a>) -> Result<(), SemAnError<'a>> {
+        ctx.check_predicate(&self.cond)?;
+        self.then.analyze(ctx)?;
+        match &self.els {
+            Some(Else::If(if_stmt)) => if_stmt.analyze(ctx)?,
+            Some(Else::Block(block)) => block.analyze(ctx)?,
+            None => (),
+        }
         Ok(())
     }
 }
 
-impl<'a> Analyzable<'a> for VarAss<'a> {
+impl<'a> Analyzable<'a> for While<'a> {
     fn analyze(&'a self, ctx: &mut SemanticAnalyzer<'a>) -> Result<(), SemAnError<'a>> {
-        //self.value.analyze(ctx)?;
-        //FIXME: this is wrong. why do i not add these symbols to the scope
-        match &self.value {
-            Expr::StEx(st_ex) => {
-                // TODO: 
-                ctx.add_symbol(self.id, None);
-                st_ex.analyze(ctx)?
-            },
-            Expr::Prim(prim) => ctx.add_prim(prim, self.id)?,
-            Expr::Bin(bin) => {
-                // TODO: 
-                ctx.add_symbol(self.id, None);
-                bin.analyze(ctx)?
-            },
+        ctx.check_predicate(&self.cond)?;
+        ctx.loop_depth += 1;
+        let res = self.body.analyze(ctx);
+        ctx.loop_depth -= 1;
+        res
+    }
+}
+
+impl<'a> Analyzable<'a> for For<'a> {
+    fn analyze(&'a self, ctx: &mut SemanticAnalyzer<'a>) -> Result<(), SemAnError<'a>> {
+        // the loop variable takes the type of the bounds, literals adapt to the other bounds
+        let mut bounds: Vec<&'a Expr<'a>> = [Some(&self.from), Some(&self.to), self.step.as_ref()]
+            .into_iter()
+            .flatten()
+            .collect();
+        bounds.sort_by_key(|b| is_num_lit(b));
+        let mut pt = None;
+        for bound in bounds {
+            let got = bound.type_check(ctx, pt)?;
+            if !got.is_int() {
+                return Err(SemAnErrorKind::ForBoundNotInteger(self.id, got).at(bound.span));
+            }
+            match pt {
+                Some(exp) if exp != got => {
+                    let kind = SemAnErrorKind::ForBoundMismatch(self.id, exp, got);
+                    return Err(kind.at(bound.span));
+                }
+                _ => pt = Some(got),
+            }
+        }
+        let pt = pt.unwrap_or(PrimType::N8);
+        self.ty.set(Some(pt));
+        // other steps are checked when the loop starts
+        if let Some(step) = &self.step {
+            if const_int(step).is_some_and(|v| v <= 0) {
+                return Err(SemAnErrorKind::ForStepNotPositive(self.id).at(step.span));
+            }
+        }
+
+        ctx.scope_stack.push(HashMap::new());
+        self.decl
+            .set(Some(ctx.add_var(self.id, pt, false, self.span)));
+        ctx.loop_vars.push(self.id);
+        ctx.loop_depth += 1;
+        let res = self.body.analyze(ctx);
+        ctx.loop_depth -= 1;
+        ctx.loop_vars.pop();
+        ctx.scope_stack.pop();
+        res
+    }
+}
+
+impl<'a> Analyzable<'a> for Ret<'a> {
+    fn analyze(&'a self, ctx: &mut SemanticAnalyzer<'a>) -> Result<(), SemAnError<'a>> {
+        match ctx.ret_stack.last().copied() {
+            None => Err(SemAnErrorKind::RetOutsideFun.at(self.span)),
+            Some(RetTarget::Fun(id, None)) => Err(SemAnErrorKind::RetInVoidFun(id).at(self.span)),
+            Some(RetTarget::Fun(id, Some(pt))) => {
+                let got = self.expr.type_check(ctx, Some(pt))?;
+                if got != pt {
+                    return Err(SemAnErrorKind::RetTypeMismatch(id, pt, got).at(self.expr.span));
+                }
+                Ok(())
+            }
+            Some(RetTarget::Block { hint, pt }) => {
+                let got = self.expr.type_check(ctx, pt.or(hint))?;
+                match pt {
+                    Some(pt) if pt != got => {
+                        Err(SemAnErrorKind::BlockTypeMismatch(pt, got).at(self.expr.span))
+                    }
+                    Some(_) => Ok(()),
+                    None => {
+                        if let Some(RetTarget::Block { pt, .. }) = ctx.ret_stack.last_mut() {
+                            *pt = Some(got);
+                        }
+                        Ok(())
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Analyzable<'a> for Reass<'a> {
+    fn analyze(&'a self, ctx: &mut SemanticAnalyzer<'a>) -> Result<(), SemAnError<'a>> {
+        let Some((decl, symbol)) = ctx.lookup(self.id) else {
+            return Err(SemAnErrorKind::ReassNotDefined(self.id).at(self.span));
+        };
+        if ctx.loop_vars.contains(&self.id) {
+            return Err(SemAnErrorKind::AssignLoopVar(self.id).at(self.span));
+        }
+        let decl_span = ctx.decls[decl].span;
+        let (pt, mutable) = match symbol {
+            Symbol::Var { pt, mutable } => (pt, mutable),
+            Symbol::Fun { .. } => {
+                return Err(SemAnErrorKind::NotAVariable(self.id, decl_span).at(self.span))
+            }
+            Symbol::Unknown => return Err(SemAnErrorKind::FollowUp.at(self.span)),
+        };
+        if !mutable {
+            return Err(SemAnErrorKind::AssignImmutable(self.id, decl_span).at(self.span));
+        }
+        self.decl.set(Some(decl));
+        let got = self.value.type_check(ctx, Some(pt))?;
+        if got != pt {
+            return Err(SemAnErrorKind::ReassTypeMismatch(self.id, pt, got).at(self.value.span));
         }
         Ok(())
     }
 }
 
+impl<'a> Analyzable<'a> for VarAss<'a> {
+    fn analyze(&'a self, ctx: &mut SemanticAnalyzer<'a>) -> Result<(), SemAnError<'a>> {
+        if ctx.loop_vars.contains(&self.id) {
+            return Err(SemAnErrorKind::AssignLoopVar(self.id).at(self.span));
+        }
+        // the value is checked before adding the symbol, so it still refers to a shadowed one
+        let res = self
+            .value
+            .type_check(ctx, self.pt)
+            .map_err(|e| match e.kind {
+                SemAnErrorKind::TokenNotDefined(id) => {
+                    SemAnErrorKind::AssignTokenNotDefined(id, self.id).at(e.span)
+                }
+                _ => e,
+            })
+            .and_then(|got| match self.pt {
+                Some(pt) if pt != got => {
+                    Err(SemAnErrorKind::VarAssTypeMismatch(self.id, pt, got).at(self.value.span))
+                }
+                _ => Ok(got),
+            });
+        // the variable is still declared, so later uses don't fail as well
+        let symbol = match res.as_ref().ok().copied().or(self.pt) {
+            Some(pt) => {
+                self.ty.set(Some(pt));
+                Symbol::Var {
+                    pt,
+                    mutable: self.mutable,
+                }
+            }
+            None => Symbol::Unknown,
+        };
+        self.decl
+            .set(Some(ctx.add_symbol(self.id, symbol, self.span)));
+        res.map(|_| ())
+    }
+}
+
+impl<'a> Typeable<'a> for Un<'a> {
+    fn type_check(
+        &'a self,
+        ctx: &mut SemanticAnalyzer<'a>,
+        hint: Option<PrimType>,
+    ) -> Result<PrimType, SemAnError<'a>> {
+        let pt = match self.op {
+            UnOperator::Nöd => self.expr.type_check(ctx, Some(PrimType::Boolean))?,
+            // number literals default to Z8 here, as N8 can't be negated
+            UnOperator::Minus => {
+                let hint = hint.filter(|pt| pt.is_num()).or(Some(PrimType::Z8));
+                match &self.expr.kind {
+                    // the negative value has to fit, so `minus 128 als Z8` is fine
+                    ExprKind::Prim(Prim::Int(v)) => {
+                        let pt = int_lit_type(-v, self.expr.span, hint)?;
+                        self.expr.ty.set(Some(pt));
+                        pt
+                    }
+                    _ => self.expr.type_check(ctx, hint)?,
+                }
+            }
+        };
+        let ok = match self.op {
+            UnOperator::Nöd => pt == PrimType::Boolean,
+            UnOperator::Minus => pt.is_float() || pt.is_signed(),
+        };
+        if !ok {
+            return Err(SemAnErrorKind::UnOperandType(self.op, pt).at(self.expr.span));
+        }
+        Ok(pt)
+    }
+}
+
+impl<'a> Typeable<'a> for Cast<'a> {
+    fn type_check(
+        &'a self,
+        ctx: &mut SemanticAnalyzer<'a>,
+        _hint: Option<PrimType>,
+    ) -> Result<PrimType, SemAnError<'a>> {
+        // number literals take on the target type, so `300 als N16` isn't out of range
+        let from = self.expr.type_check(ctx, Some(self.pt))?;
+        if !castable(from, self.pt) {
+            return Err(SemAnErrorKind::CastNotAllowed(from, self.pt).at(self.expr.span));
+        }
+        Ok(self.pt)
+    }
+}
+
+/// Type of the integer literal `v`, it's the numeric `hint` or N8, Z8 for negative literals
+fn int_lit_type<'a>(
+    v: i128,
+    span: Span,
+    hint: Option<PrimType>,
+) -> Result<PrimType, SemAnError<'a>> {
+    let default = if v < 0 { PrimType::Z8 } else { PrimType::N8 };
+    let pt = hint.filter(|pt| pt.is_num()).unwrap_or(default);
+    match pt.int_range() {
+        Some((min, max)) if v < min || v > max => {
+            Err(SemAnErrorKind::LiteralOutOfRange(pt).at(span))
+        }
+        _ => Ok(pt),
+    }
+}
+
+/// Value of `expr` if it's an integer literal, possibly negated
+pub fn const_int(expr: &Expr) -> Option<i128> {
+    match &expr.kind {
+        ExprKind::Prim(Prim::Int(v)) => Some(*v),
+        ExprKind::Un(un) if un.op == UnOperator::Minus => const_int(&un.expr).map(|v| -v),
+        _ => None,
+    }
+}
+
+/// Whether `als` can convert values of type `from` to `to`
+fn castable(from: PrimType, to: PrimType) -> bool {
+    from == to
+        || (from.is_num() || from == PrimType::Boolean) && (to.is_num() || to == PrimType::String)
+}
+
+/// Whether the type of `expr` only depends on integer literals, which adapt to the numeric type
+/// they're used with. Float literals are always R8.
+fn is_num_lit(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Prim(Prim::Int(_)) => true,
+        ExprKind::Un(un) => un.op == UnOperator::Minus && is_num_lit(&un.expr),
+        ExprKind::Bin(bin) => match bin.op {
+            BinOperator::Rescht
+            | BinOperator::Hoch
+            | BinOperator::Mal
+            | BinOperator::Durch
+            | BinOperator::Plus
+            | BinOperator::Minus => is_num_lit(&bin.lhs) && is_num_lit(&bin.rhs),
+            _ => false,
+        },
+        ExprKind::Cond(cond) => is_num_lit(&cond.then) && is_num_lit(&cond.els),
+        _ => false,
+    }
+}
+
 pub struct SemanticAnalyzer<'a> {
     ast: &'a Program<'a>,
     scope_stack: Vec<Scope<'a>>,
+    ret_stack: Vec<RetTarget<'a>>,
+    loop_depth: usize,
+    loop_vars: Vec<&'a str>,
+    decls: Vec<Decl<'a>>,
+    errors: Vec<SemAnError<'a>>,
+    /// number of statements skipped because of an unknown type, they aren't reported
+    follow_ups: usize,
+    /// top level function whose body is being analyzed
+    cur_fun: Option<DeclId>,
+    /// top level variables read and top level functions called by each top level function
+    uses: HashMap<DeclId, Vec<DeclId>>,
+    /// top level functions called by top level code, with the number of declarations at the call
+    top_calls: Vec<(DeclId, Span, usize)>,
+    max_errors: usize,
 }
 
-// TODO: annotate AST
 impl<'a> SemanticAnalyzer<'a> {
     pub fn new(ast: &'a Program<'a>) -> Self {
         Self {
             ast,
             scope_stack: vec![HashMap::new()],
+            ret_stack: vec![],
+            loop_depth: 0,
+            loop_vars: vec![],
+            decls: vec![],
+            errors: vec![],
+            follow_ups: 0,
+            cur_fun: None,
+            uses: HashMap::new(),
+            top_calls: vec![],
+            max_errors: usize::MAX,
         }
     }
 
-    pub fn analyze(&'a mut self) -> Result<(), SemAnError<'a>> {
-        // TODO: hoisting
-        // TODO: type checking
+    /// Stop after `max_errors` errors
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors.max(1);
+        self
+    }
+
+    pub fn analyze(mut self) -> Result<TypedProgram<'a>, Vec<SemAnError<'a>>> {
+        // top level functions can be called before they're declared
         for stmt in self.ast.iter() {
-            stmt.analyze(self)?;
+            if let Stmt::FunAss(fun_ass) = stmt {
+                match self.lookup(fun_ass.id) {
+                    Some((decl, _)) => {
+                        let kind = SemAnErrorKind::FunctionDeclaredTwice(
+                            fun_ass.id,
+                            self.decls[decl].span,
+                        );
+                        self.errors.push(kind.at(fun_ass.span));
+                    }
+                    None => self.declare_fun(fun_ass),
+                }
+            }
+        }
+        self.analyze_stmts(self.ast);
+        self.check_top_calls();
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+        Ok(TypedProgram {
+            ast: self.ast,
+            decls: self.decls,
+        })
+    }
+
+    /// Analyzes statements one by one, a statement with an error is reported and skipped
+    fn analyze_stmts(&mut self, stmts: &'a [Stmt<'a>]) {
+        for stmt in stmts.iter() {
+            if self.errors.len() >= self.max_errors {
+                break;
+            }
+            match stmt.analyze(self) {
+                Ok(()) => (),
+                Err(SemAnError {
+                    kind: SemAnErrorKind::FollowUp,
+                    ..
+                }) => self.follow_ups += 1,
+                Err(err) => self.errors.push(err),
+            }
+        }
+    }
+
+    /// Top level variables are set at the start of `chuchichäschtli` in the order they're
+    /// written, but a hoisted function called before that can read one declared after the call
+    fn check_top_calls(&mut self) {
+        for (fun, span, set) in std::mem::take(&mut self.top_calls) {
+            if self.errors.len() >= self.max_errors {
+                break;
+            }
+            let mut seen = vec![fun];
+            let mut todo = vec![fun];
+            while let Some(cur) = todo.pop() {
+                for &used in self.uses.get(&cur).into_iter().flatten() {
+                    match self.decls[used].symbol {
+                        // declarations are numbered in order, so the ones after the call are unset
+                        Symbol::Var { .. } if used >= set => {
+                            let decl = &self.decls[used];
+                            let kind = SemAnErrorKind::ReadBeforeSet(decl.id, decl.span);
+                            self.errors.push(kind.at(span));
+                            todo.clear();
+                            break;
+                        }
+                        Symbol::Fun { .. } if !seen.contains(&used) => {
+                            seen.push(used);
+                            todo.push(used);
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Records the use of a top level variable or function for [Self::check_top_calls]
+    fn record_use(&mut self, id: &str, decl: DeclId, span: Span) {
+        if self.scope_stack[0].get(id) != Some(&decl) {
+            return;
+        }
+        match (self.cur_fun, self.decls[decl].symbol) {
+            (Some(fun), _) => self.uses.entry(fun).or_default().push(decl),
+            (None, Symbol::Fun { .. }) => self.top_calls.push((decl, span, self.decls.len())),
+            _ => (),
+        }
+    }
+
+    /// Checks two expressions which need to have the same type. Integer literals on one side take
+    /// on the type of the other side.
+    fn type_check_pair(
+        &mut self,
+        lhs: &'a Expr<'a>,
+        rhs: &'a Expr<'a>,
+        hint: Option<PrimType>,
+    ) -> Result<(PrimType, PrimType), SemAnError<'a>> {
+        if is_num_lit(lhs) && !is_num_lit(rhs) {
+            let rhs = rhs.type_check(self, hint)?;
+            Ok((lhs.type_check(self, Some(rhs))?, rhs))
+        } else {
+            let lhs = lhs.type_check(self, hint)?;
+            Ok((lhs, rhs.type_check(self, Some(lhs))?))
+        }
+    }
+
+    fn check_predicate(&mut self, cond: &'a Expr<'a>) -> Result<(), SemAnError<'a>> {
+        let pt = cond.type_check(self, Some(PrimType::Boolean))?;
+        if pt != PrimType::Boolean {
+            return Err(SemAnErrorKind::CondNotBoolean(pt).at(cond.span));
         }
         Ok(())
     }
 
-    fn add_prim(&mut self, prim: &'a Prim, symbol: &'a str) -> Result<(), SemAnError<'a>> {
+    /// Checks a call and returns what the function `git`, if anything
+    fn call_type(&mut self, call: &'a Call<'a>) -> Result<Option<PrimType>, SemAnError<'a>> {
+        let (params, ret) = match self.lookup(call.id) {
+            Some((decl, Symbol::Fun { args, ret })) => {
+                call.decl.set(Some(decl));
+                self.record_use(call.id, decl, call.span);
+                (Some(args), ret)
+            }
+            Some((decl, Symbol::Var { .. })) => {
+                let decl_span = self.decls[decl].span;
+                return Err(SemAnErrorKind::NotAFunction(call.id, decl_span).at(call.span));
+            }
+            Some((_, Symbol::Unknown)) => return Err(SemAnErrorKind::FollowUp.at(call.span)),
+            None if BUILTINS.contains(&call.id) => (None, None),
+            None => return Err(SemAnErrorKind::FunctionNotDefined(call.id).at(call.span)),
+        };
+        if let Some(params) = params {
+            if params.len() != call.args.len() {
+                let kind = SemAnErrorKind::ArgCountMismatch(call.id, params.len(), call.args.len());
+                return Err(kind.at(call.span));
+            }
+        }
+        for (i, arg) in call.args.iter().enumerate() {
+            let param = params.and_then(|p| p.get(i));
+            let got = arg
+                .type_check(self, param.map(|p| p.pt))
+                .map_err(|e| match e.kind {
+                    SemAnErrorKind::TokenNotDefined(t) | SemAnErrorKind::FunctionNotDefined(t) => {
+                        SemAnErrorKind::ArgNotDefined(call.id, t).at(e.span)
+                    }
+                    _ => e,
+                })?;
+            if let Some(param) = param {
+                if param.pt != got {
+                    let kind = SemAnErrorKind::ArgTypeMismatch(call.id, param, got);
+                    return Err(kind.at(arg.span));
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Checks a block used as an expression and returns the type it `gib`t
+    fn block_type(
+        &mut self,
+        block: &'a Block<'a>,
+        hint: Option<PrimType>,
+    ) -> Result<PrimType, SemAnError<'a>> {
+        self.ret_stack.push(RetTarget::Block { hint, pt: None });
+        let errors = self.errors.len();
+        let follow_ups = self.follow_ups;
+        let res = block.analyze(self);
+        let target = self.ret_stack.pop();
+        res?;
+        // the value of a block with errors is unknown, so the statement using it fails as well
+        if self.errors.len() > errors {
+            if let Some(err) = self.errors.pop() {
+                return Err(err);
+            }
+        }
+        if self.follow_ups > follow_ups {
+            return Err(SemAnErrorKind::FollowUp.at(block.span));
+        }
+        match target {
+            Some(RetTarget::Block { pt: Some(pt), .. }) => Ok(pt),
+            _ => Err(SemAnErrorKind::BlockNoValue.at(block.span)),
+        }
+    }
+
+    fn prim_type(
+        &mut self,
+        prim: &'a Prim<'a>,
+        span: Span,
+        hint: Option<PrimType>,
+    ) -> Result<PrimType, SemAnError<'a>> {
         match prim {
-            Prim::Str(_) => self.add_symbol(symbol, Some(PrimType::String).as_ref()),
-            Prim::Bool(_) => self.add_symbol(symbol, Some(PrimType::Boolean).as_ref()),
-            Prim::R8(_) => self.add_symbol(symbol, Some(PrimType::R8).as_ref()),
-            Prim::Id(id) => {
-                if self.has_symbol(id) {
-                    self.add_symbol(symbol, self.get_symbol(id));
-                } else {
-                    return Err(SemAnError::AssignTokenNotDefined(id, symbol));
+            Prim::Bool(_) => Ok(PrimType::Boolean),
+            Prim::Str(_) => Ok(PrimType::String),
+            Prim::Int(v) => int_lit_type(*v, span, hint),
+            Prim::Float(v) => {
+                let pt = hint.filter(|pt| pt.is_float()).unwrap_or(PrimType::R8);
+                match pt.float_max() {
+                    Some(max) if v.abs() > max => {
+                        Err(SemAnErrorKind::LiteralOutOfRange(pt).at(span))
+                    }
+                    _ => Ok(pt),
                 }
             }
+            Prim::Id(id, decl) => match self.lookup(id) {
+                Some((d, Symbol::Var { pt, .. })) => {
+                    decl.set(Some(d));
+                    self.record_use(id, d, span);
+                    Ok(pt)
+                }
+                Some((d, Symbol::Fun { .. })) => {
+                    Err(SemAnErrorKind::NotAVariable(id, self.decls[d].span).at(span))
+                }
+                Some((_, Symbol::Unknown)) => Err(SemAnErrorKind::FollowUp.at(span)),
+                None => Err(SemAnErrorKind::TokenNotDefined(id).at(span)),
+            },
         }
-        Ok(())
     }
 
-    fn add_symbol(&mut self, symbol: &'a str, value: Option<&'a PrimType>) {
+    fn declare_fun(&mut self, fun_ass: &'a FunAss<'a>) {
+        let decl = self.add_symbol(
+            fun_ass.id,
+            Symbol::Fun {
+                args: &fun_ass.args,
+                ret: fun_ass.ret,
+            },
+            fun_ass.span,
+        );
+        fun_ass.decl.set(Some(decl));
+    }
+
+    fn add_var(&mut self, symbol: &'a str, pt: PrimType, mutable: bool, span: Span) -> DeclId {
+        self.add_symbol(symbol, Symbol::Var { pt, mutable }, span)
+    }
+
+    fn add_symbol(&mut self, symbol: &'a str, value: Symbol<'a>, span: Span) -> DeclId {
         // FIXME: confusion
         if let Some(l) = self.scope_stack.last() {
-            if let Some(s) = l.get(symbol) {
+            if let Some(d) = l.get(symbol) {
+                let prev = &self.decls[*d];
                 eprintln!(
-                    "WARNING: Shadowing previously defined variable `{}` ({:?}) with new {}",
-                    symbol,
-                    s,
-                    value.map_or("Unknown".to_string(), |v| v.to_string())
+                    "WARNING at {}: Shadowing previously defined variable `{}` ({}, at {}) with new {}",
+                    span, symbol, prev.symbol, prev.span, value
                 )
             }
         }
+        let decl = self.decls.len();
+        self.decls.push(Decl {
+            id: symbol,
+            symbol: value,
+            span,
+        });
         if let Some(cur) = self.scope_stack.last_mut() {
-            cur.insert(symbol, value);
-        }
-    }
-
-    fn has_symbol(&self, symbol: &str) -> bool {
-        for item in self.scope_stack.iter().rev() {
-            if item.contains_key(symbol) {
-                return true;
-            }
+            cur.insert(symbol, decl);
         }
-        false
+        decl
     }
 
-    fn get_symbol(&self, symbol: &str) -> Option<&'a PrimType> {
+    fn lookup(&self, symbol: &str) -> Option<(DeclId, Symbol<'a>)> {
         for item in self.scope_stack.iter().rev() {
-            if let Some(v) = item.get(symbol) {
-                return *v;
+            if let Some(d) = item.get(symbol) {
+                return Some((*d, self.decls[*d].symbol));
             }
         }
         None
@@ -1,95 +1,204 @@
-use crate::parser::{
-    Arg, Bin, BinOperator, Block, Call, Expr, FunAss, Prim, PrimType, Program, Ret, StEx, Stmt,
-    VarAss,
+use crate::{
+    parser::{
+        Arg, Bin, BinOperator, Block, Call, Cast, Cond, DeclId, Else, Expr, ExprKind, For, FunAss,
+        If, Prim, PrimType, Reass, Ret, StEx, Stmt, Un, UnOperator, VarAss, While,
+    },
+    seman::{const_int, TypedProgram},
 };
 
 pub trait Transpileable<'a> {
-    fn transpile(&'a self) -> String;
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String;
 }
 
 impl<'a> Transpileable<'a> for Stmt<'a> {
-    fn transpile(&'a self) -> String {
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
         match self {
-            Stmt::FunAss(fun_ass) => fun_ass.transpile(),
-            Stmt::VarAss(var_ass) => var_ass.transpile() + ";\n",
-            Stmt::StEx(st_ex) => st_ex.transpile() + ";\n",
-            Stmt::Ret(ret) => ret.transpile() + ";\n",
+            Stmt::FunAss(fun_ass) => fun_ass.transpile(ctx),
+            Stmt::VarAss(var_ass) => var_ass.transpile(ctx) + ";\n",
+            Stmt::Reass(reass) => reass.transpile(ctx) + ";\n",
+            Stmt::StEx(st_ex) => st_ex.transpile(ctx) + ";\n",
+            Stmt::Ret(ret) => ret.transpile(ctx) + ";\n",
+            Stmt::If(if_stmt) => if_stmt.transpile(ctx) + "\n",
+            Stmt::While(while_stmt) => while_stmt.transpile(ctx) + "\n",
+            Stmt::For(for_stmt) => for_stmt.transpile(ctx) + "\n",
+            Stmt::Break(_) => "break;\n".to_string(),
+            Stmt::Continue(_) => "continue;\n".to_string(),
         }
     }
 }
 
 impl<'a> Transpileable<'a> for Arg<'a> {
-    fn transpile(&'a self) -> String {
-        let pt = self.pt.transpile();
-        let id = self.id;
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
+        let pt = self.pt.c_type();
+        let id = ctx.c_name(self.id, self.decl.get());
         format!("{pt} {id}")
     }
 }
 
 impl<'a> Transpileable<'a> for Block<'a> {
-    fn transpile(&'a self) -> String {
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
         self.stmts
             .iter()
-            .map(|s| s.transpile())
+            .map(|s| s.transpile(ctx))
             .collect::<Vec<String>>()
             .join("")
     }
 }
 
-impl<'a> Transpileable<'a> for PrimType {
-    fn transpile(&'a self) -> String {
+impl PrimType {
+    /// Suffix of the names of prelude functions for this type
+    fn name_suffix(&self) -> &'static str {
+        match self {
+            PrimType::String => "zeiche",
+            PrimType::R8 => "r8",
+            PrimType::R4 => "r4",
+            PrimType::N8 => "n8",
+            PrimType::N16 => "n16",
+            PrimType::N32 => "n32",
+            PrimType::N64 => "n64",
+            PrimType::Z8 => "z8",
+            PrimType::Z16 => "z16",
+            PrimType::Z32 => "z32",
+            PrimType::Z64 => "z64",
+            PrimType::Boolean => "wahrheit",
+        }
+    }
+    fn c_type(&self) -> String {
         match self {
             PrimType::String => "char*".to_string(),
-            PrimType::R8 => "float".to_string(),
-            PrimType::N8 => "unsigned int".to_string(),
-            PrimType::Z8 => "int".to_string(),
+            PrimType::R8 => "double".to_string(),
+            PrimType::R4 => "float".to_string(),
+            PrimType::N8 => "uint8_t".to_string(),
+            PrimType::N16 => "uint16_t".to_string(),
+            PrimType::N32 => "uint32_t".to_string(),
+            PrimType::N64 => "uint64_t".to_string(),
+            PrimType::Z8 => "int8_t".to_string(),
+            PrimType::Z16 => "int16_t".to_string(),
+            PrimType::Z32 => "int32_t".to_string(),
+            PrimType::Z64 => "int64_t".to_string(),
             PrimType::Boolean => "int".to_string(),
         }
     }
 }
 
-impl<'a> Transpileable<'a> for FunAss<'a> {
-    fn transpile(&'a self) -> String {
-        let ret = self.ret.map_or("void".to_string(), |pt| pt.transpile());
+impl<'a> FunAss<'a> {
+    fn signature(&'a self, ctx: &mut Transpiler<'a>) -> String {
+        // C requires `main` to return `int`, smaller return values are promoted
+        let ret = match (self.id, self.ret) {
+            ("chuchichäschtli", _) => "int".to_string(),
+            (_, ret) => ret.map_or("void".to_string(), |pt| pt.c_type()),
+        };
         let args = self
             .args
             .iter()
-            .map(|a| a.transpile())
+            .map(|a| a.transpile(ctx))
             .collect::<Vec<String>>()
             .join(", ");
-        let body = self.body.transpile();
-        let id = match self.id {
-            "chuchichäschtli" => "main",
-            i => i,
-        };
-        format!("{ret} {id}({args}) {{\n{body}\n}}")
+        let id = ctx.fun_name(self.id, self.decl.get());
+        format!("{ret} {id}({args})")
+    }
+
+    /// Definition of the function, `init` runs before its body
+    fn transpile_with(&'a self, ctx: &mut Transpiler<'a>, init: &str) -> String {
+        let signature = self.signature(ctx);
+        ctx.ret_targets.push(None);
+        let body = self.body.transpile(ctx);
+        ctx.ret_targets.pop();
+        format!("{signature} {{\n{init}{body}\n}}")
+    }
+}
+
+impl<'a> Transpileable<'a> for FunAss<'a> {
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
+        self.transpile_with(ctx, "")
+    }
+}
+
+impl<'a> Block<'a> {
+    /// A block used as a value becomes a GNU statement expression, `gib` stores the value in a
+    /// variable and jumps to its end
+    fn transpile_value(&'a self, ctx: &mut Transpiler<'a>, pt: Option<PrimType>) -> String {
+        let n = ctx.block_values;
+        ctx.block_values += 1;
+        let pt = pt.map_or("int".to_string(), |pt| pt.c_type());
+        ctx.ret_targets.push(Some(n));
+        let body = self.transpile(ctx);
+        ctx.ret_targets.pop();
+        format!("({{\n{pt} haessig_wert_{n} = 0;\n{body}haessig_gib_{n}:;\nhaessig_wert_{n};\n}})")
     }
 }
 
 impl<'a> Transpileable<'a> for Expr<'a> {
-    fn transpile(&'a self) -> String {
-        match self {
-            Expr::StEx(st_ex) => st_ex.transpile(),
-            Expr::Prim(prim) => prim.transpile(),
-            Expr::Bin(bin) => bin.transpile(),
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
+        match &self.kind {
+            ExprKind::StEx(StEx::Block(block)) => block.transpile_value(ctx, self.ty.get()),
+            ExprKind::StEx(st_ex) => st_ex.transpile(ctx),
+            ExprKind::Prim(Prim::Int(v)) => int_lit(*v, self.ty.get()),
+            // `{:?}` keeps the decimal point of whole numbers, e.g. `3.0`, and writes `1e300`
+            ExprKind::Prim(Prim::Float(v)) => format!("{:?}{}", v, float_suffix(self.ty.get())),
+            ExprKind::Prim(prim) => prim.transpile(ctx),
+            ExprKind::Un(un) => un.transpile(ctx),
+            ExprKind::Bin(bin) => bin.transpile(ctx),
+            ExprKind::Cond(cond) => cond.transpile(ctx),
+            ExprKind::Cast(cast) => cast.transpile(ctx),
+        }
+    }
+}
+
+/// C literal for the integer `v` of type `pt`
+fn int_lit(v: i128, pt: Option<PrimType>) -> String {
+    match pt {
+        // integer literals used as floats need a decimal point, or C divides them as integers
+        Some(pt) if pt.is_float() => format!("{}.0{}", v, float_suffix(Some(pt))),
+        // the magnitude of INT64_MIN doesn't fit a signed C literal
+        _ if v == i64::MIN.into() => "INT64_MIN".to_string(),
+        _ if v > i64::MAX.into() => format!("{}u", v),
+        _ => v.to_string(),
+    }
+}
+
+/// Suffix of float literals of type `pt`, without one they're `double` and R4 values compare
+/// unequal to them
+fn float_suffix(pt: Option<PrimType>) -> &'static str {
+    match pt {
+        Some(PrimType::R4) => "f",
+        _ => "",
+    }
+}
+
+/// C string literal with the contents `s`, non ASCII characters stay UTF-8
+fn c_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            // octal escapes take at most three digits, so a following digit can't be swallowed
+            _ if ch.is_ascii_control() => out.push_str(&format!("\\{:03o}", ch as u32)),
+            _ => out.push(ch),
         }
     }
+    out.push('"');
+    out
 }
 
 impl<'a> Transpileable<'a> for Prim<'a> {
-    fn transpile(&'a self) -> String {
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
         match self {
             Prim::Bool(v) => (if *v { 1 } else { 0 }).to_string(),
-            // Prim::Str(v) => format!("\"{v}\""),
-            Prim::Str(v) => v.to_string(),
-            Prim::R8(v) => v.to_string(),
-            Prim::Id(v) => v.to_string(),
+            Prim::Str(v) => c_string(v),
+            Prim::Int(v) => v.to_string(),
+            Prim::Float(v) => format!("{:?}", v),
+            Prim::Id(v, decl) => ctx.c_name(v, decl.get()),
         }
     }
 }
 
-impl<'a> Transpileable<'a> for BinOperator {
-    fn transpile(&'a self) -> String {
+impl BinOperator {
+    fn c_op(&self) -> String {
         (match self {
             BinOperator::Gliich => "==",
             BinOperator::GrösserGliich => ">=",
@@ -100,8 +209,8 @@ impl<'a> Transpileable<'a> for BinOperator {
             BinOperator::Und => "&&",
             BinOperator::Oder => "||",
             BinOperator::Rescht => "%",
-            // TODO:
-            BinOperator::Hoch => "^",
+            // only used for the name of the helper, see `Bin::transpile`
+            BinOperator::Hoch => "hoch",
             BinOperator::Mal => "*",
             BinOperator::Durch => "/",
             BinOperator::Plus => "+",
@@ -111,44 +220,137 @@ impl<'a> Transpileable<'a> for BinOperator {
     }
 }
 
+impl<'a> Transpileable<'a> for Un<'a> {
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
+        match (self.op, &self.expr.kind, self.expr.ty.get()) {
+            (UnOperator::Nöd, _, _) => format!("(!{})", self.expr.transpile(ctx)),
+            // negative literals are checked by the semantic analyzer already
+            (UnOperator::Minus, ExprKind::Prim(Prim::Int(v)), pt) => {
+                format!("({})", int_lit(-v, pt))
+            }
+            // negating the smallest signed value overflows too
+            (UnOperator::Minus, _, Some(pt)) if pt.is_int() => {
+                let name = helper_name(BinOperator::Minus, pt);
+                let start = self.expr.span.start;
+                let expr = self.expr.transpile(ctx);
+                format!("{name}(0, {expr}, {}, {})", start.row + 1, start.col + 1)
+            }
+            (UnOperator::Minus, _, _) => format!("(-{})", self.expr.transpile(ctx)),
+        }
+    }
+}
+
 impl<'a> Transpileable<'a> for Bin<'a> {
-    fn transpile(&'a self) -> String {
-        let lhs = (*self.lhs).transpile();
-        let rhs = (*self.rhs).transpile();
-        let op = self.op.transpile();
-        format!("{lhs} {op} {rhs}")
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
+        let lhs = (*self.lhs).transpile(ctx);
+        let rhs = (*self.rhs).transpile(ctx);
+        let op = self.op.c_op();
+        match (self.op, self.lhs.ty.get()) {
+            (BinOperator::Gliich | BinOperator::Ungliich, Some(PrimType::String)) => {
+                format!("(strcmp({lhs}, {rhs}) {op} 0)")
+            }
+            // what happens on overflow is up to the helpers in the prelude
+            (
+                BinOperator::Plus
+                | BinOperator::Minus
+                | BinOperator::Mal
+                | BinOperator::Hoch
+                | BinOperator::Durch
+                | BinOperator::Rescht,
+                Some(pt),
+            ) if pt.is_int() => {
+                let name = helper_name(self.op, pt);
+                let start = self.lhs.span.start;
+                format!("{name}({lhs}, {rhs}, {}, {})", start.row + 1, start.col + 1)
+            }
+            (BinOperator::Hoch, Some(PrimType::R4)) => format!("powf({lhs}, {rhs})"),
+            (BinOperator::Hoch, _) => format!("pow({lhs}, {rhs})"),
+            _ => format!("({lhs} {op} {rhs})"),
+        }
+    }
+}
+
+impl<'a> Transpileable<'a> for Cast<'a> {
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
+        let expr = self.expr.transpile(ctx);
+        let Some(from) = self.expr.ty.get() else {
+            return expr;
+        };
+        match (from, self.pt) {
+            _ if from == self.pt => expr,
+            (PrimType::Boolean, PrimType::String) => format!("(({expr}) ? \"wahr\" : \"falsch\")"),
+            (_, PrimType::String) => format!("{}({expr})", to_zeiche_name(from)),
+            // C leaves floats which don't fit undefined, the helper clamps them
+            (_, to) if from.is_float() && to.is_int() => format!("{}({expr})", from_float_name(to)),
+            (_, to) => format!("(({})({expr}))", to.c_type()),
+        }
+    }
+}
+
+impl<'a> Transpileable<'a> for Cond<'a> {
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
+        let cond = self.cond.transpile(ctx);
+        let then = self.then.transpile(ctx);
+        let els = self.els.transpile(ctx);
+        format!("({cond} ? {then} : {els})")
+    }
+}
+
+impl<'a> VarAss<'a> {
+    fn c_type(&self) -> String {
+        self.ty
+            .get()
+            .or(self.pt)
+            .map_or("void".to_string(), |pt| pt.c_type())
     }
 }
 
 impl<'a> Transpileable<'a> for VarAss<'a> {
-    fn transpile(&'a self) -> String {
-        let id = self.id;
-        let pt = self.pt.map_or("void".to_string(), |pt| pt.transpile());
-        let value = self.value.transpile();
-        format!("{pt} {id} = {value}")
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
+        let id = ctx.c_name(self.id, self.decl.get());
+        let pt = self.c_type();
+        let constness = if self.mutable { "" } else { " const" };
+        let value = self.value.transpile(ctx);
+        format!("{pt}{constness} {id} = {value}")
+    }
+}
+
+impl<'a> Transpileable<'a> for Reass<'a> {
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
+        let id = ctx.c_name(self.id, self.decl.get());
+        let value = self.value.transpile(ctx);
+        format!("{id} = {value}")
     }
 }
 
 impl<'a> Transpileable<'a> for Call<'a> {
-    fn transpile(&'a self) -> String {
-        if self.id == "schreie" {
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
+        // builtins don't resolve to a declaration
+        if self.id == "schreie" && self.decl.get().is_none() {
             let (templs, args) = self
                 .args
                 .iter()
-                .map(|a| {
-                    (
-                        (match a {
-                            Expr::StEx(_) => "%s",
-                            Expr::Prim(prim) => match prim {
-                                // FIXME: needs proper impl
-                                Prim::Bool(_) | Prim::R8(_) | Prim::Id(_) => "%i",
-                                Prim::Str(_) => "%s",
-                            },
-                            Expr::Bin(_) => "%i",
+                .map(|a| match a.ty.get() {
+                    Some(PrimType::Boolean) => (
+                        "%s".to_string(),
+                        format!("({}) ? \"wahr\" : \"falsch\"", a.transpile(ctx)),
+                    ),
+                    Some(pt) if pt.is_int() && pt.is_signed() => (
+                        "%lld".to_string(),
+                        format!("(long long)({})", a.transpile(ctx)),
+                    ),
+                    Some(pt) if pt.is_int() => (
+                        "%llu".to_string(),
+                        format!("(unsigned long long)({})", a.transpile(ctx)),
+                    ),
+                    pt => (
+                        (match pt {
+                            Some(PrimType::String) => "%s",
+                            _ => "%g",
                         })
                         .to_string(),
-                        a.transpile(),
-                    )
+                        a.transpile(ctx),
+                    ),
                 })
                 .reduce(|(ta, aa), (t, a)| (ta + " " + &t, aa + ", " + &a))
                 .unwrap_or(("%s".to_string(), "\" \"".to_string()));
@@ -157,60 +359,387 @@ impl<'a> Transpileable<'a> for Call<'a> {
             let args = self
                 .args
                 .iter()
-                .map(|a| a.transpile())
+                .map(|a| a.transpile(ctx))
                 .collect::<Vec<String>>()
                 .join(", ");
-            format!("{}({})", self.id, args)
+            format!("{}({})", ctx.fun_name(self.id, self.decl.get()), args)
         }
     }
 }
 
 impl<'a> Transpileable<'a> for StEx<'a> {
-    fn transpile(&'a self) -> String {
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
         match self {
-            StEx::Call(call) => call.transpile(),
-            StEx::Block(block) => block.transpile(),
+            StEx::Call(call) => call.transpile(ctx),
+            StEx::Block(block) => format!("{{\n{}}}", block.transpile(ctx)),
         }
     }
 }
 
+impl<'a> Transpileable<'a> for If<'a> {
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
+        let cond = self.cond.transpile(ctx);
+        let then = self.then.transpile(ctx);
+        let els = match &self.els {
+            Some(Else::If(if_stmt)) => format!(" else {}", if_stmt.transpile(ctx)),
+            Some(Else::Block(block)) => format!(" else {{\n{}}}", block.transpile(ctx)),
+            None => "".to_string(),
+        };
+        format!("if ({cond}) {{\n{then}}}{els}")
+    }
+}
+
+impl<'a> Transpileable<'a> for While<'a> {
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
+        let cond = self.cond.transpile(ctx);
+        let body = self.body.transpile(ctx);
+        format!("while ({cond}) {{\n{body}}}")
+    }
+}
+
+impl<'a> Transpileable<'a> for For<'a> {
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
+        let id = ctx.c_name(self.id, self.decl.get());
+        let pt = self.ty.get().unwrap_or(PrimType::N8).c_type();
+        let from = self.from.transpile(ctx);
+        let to = self.to.transpile(ctx);
+        let step = self
+            .step
+            .as_ref()
+            .map_or("1".to_string(), |s| s.transpile(ctx));
+        // the analyzer only rejects literal steps which aren't positive
+        let check = match &self.step {
+            Some(step) if const_int(step).is_none() => {
+                let start = step.span.start;
+                format!(
+                    "if ({id}__schritt <= 0) {{\nhaessig_schritt({}, {});\n}}\n",
+                    start.row + 1,
+                    start.col + 1
+                )
+            }
+            _ => "".to_string(),
+        };
+        let body = self.body.transpile(ctx);
+        // bounds and step are evaluated once, before the first iteration. The last step goes to
+        // `bis` at most, so the loop variable can't overflow.
+        format!(
+            "{{\n{pt} {id} = {from};\n{pt} const {id}__bis = {to};\n\
+             {pt} const {id}__schritt = {step};\n{check}\
+             for (; {id} < {id}__bis; {id} = (uint64_t){id}__bis - (uint64_t){id} > \
+             (uint64_t){id}__schritt ? {id} + {id}__schritt : {id}__bis) {{\n{body}}}\n}}"
+        )
+    }
+}
+
 impl<'a> Transpileable<'a> for Ret<'a> {
-    fn transpile(&'a self) -> String {
-        let expr = self.expr.transpile();
-        format!("return {expr}")
+    fn transpile(&'a self, ctx: &mut Transpiler<'a>) -> String {
+        let expr = self.expr.transpile(ctx);
+        match ctx.ret_targets.last() {
+            Some(Some(n)) => format!("{{\nhaessig_wert_{n} = {expr};\ngoto haessig_gib_{n};\n}}"),
+            _ => format!("return {expr}"),
+        }
+    }
+}
+
+/// What integer `plus`, `minus`, `mal` and `hoch` do when the result doesn't fit the type
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// the result wraps around, e.g. `255 plus 1` as N8 is `0`
+    #[default]
+    Wrap,
+    /// the program stops with an error pointing at the operation
+    Trap,
+    /// the result is clamped to the smallest or largest value of the type
+    Saturate,
+}
+
+const INT_TYPES: [PrimType; 8] = [
+    PrimType::N8,
+    PrimType::N16,
+    PrimType::N32,
+    PrimType::N64,
+    PrimType::Z8,
+    PrimType::Z16,
+    PrimType::Z32,
+    PrimType::Z64,
+];
+
+/// Name of the prelude function formatting numbers of type `pt` as Zeiche
+fn to_zeiche_name(pt: PrimType) -> &'static str {
+    if pt.is_float() {
+        "haessig_zeiche_r"
+    } else if pt.is_signed() {
+        "haessig_zeiche_z"
+    } else {
+        "haessig_zeiche_n"
     }
 }
 
+/// Prelude function formatting numbers as Zeiche, the strings are never freed
+fn to_zeiche(name: &str) -> String {
+    let (t, fmt) = match name {
+        "haessig_zeiche_r" => ("double", "%g"),
+        "haessig_zeiche_z" => ("long long", "%lld"),
+        _ => ("unsigned long long", "%llu"),
+    };
+    format!(
+        "static char* {name}({t} v) {{\nchar* s = malloc(32);\nsnprintf(s, 32, \"{fmt}\", v);\n\
+         return s;\n}}\n"
+    )
+}
+
+/// Name of the prelude function converting floats to integers of type `pt`
+fn from_float_name(pt: PrimType) -> String {
+    format!("haessig_als_{}", pt.name_suffix())
+}
+
+/// Prelude function converting floats to integers of type `pt`. Like Rust's `as` it rounds
+/// towards zero, clamps to the range of `pt` and turns NaN into 0.
+fn from_float(pt: PrimType) -> String {
+    let name = from_float_name(pt);
+    let t = pt.c_type();
+    let limit = t.trim_end_matches("_t").to_uppercase();
+    let min = if pt.is_signed() {
+        format!("{limit}_MIN")
+    } else {
+        "0".to_string()
+    };
+    format!(
+        "static {t} {name}(double v) {{\nif (v != v) {{\nreturn 0;\n}}\nif (v <= {min}) {{\n\
+         return {min};\n}}\nif (v >= {limit}_MAX) {{\nreturn {limit}_MAX;\n}}\nreturn ({t})v;\n}}\n"
+    )
+}
+
+/// Stops the program when the step of a `für` loop isn't greater than 0
+const STEP_ERROR: &str = "static void haessig_schritt(int row, int col) {\nfprintf(stderr, \
+    \"Fehler i Ziile %d, Spalte %d: dr `schritt` muss grösser als 0 sii\\n\", row, col);\n\
+    exit(EXIT_FAILURE);\n}\n";
+
+/// Stops the program when an integer is divided by zero
+const DIVISION_ERROR: &str = "static void haessig_durch_null(int row, int col) {\nfprintf(stderr, \
+    \"Fehler i Ziile %d, Spalte %d: dur 0 cha mer nöd teile\\n\", row, col);\n\
+    exit(EXIT_FAILURE);\n}\n";
+
+/// Stops the program when a checked operation overflows with `--overflow=trap`
+const OVERFLOW_ERROR: &str = "static void haessig_overflow(const char* op, const char* type, \
+    int row, int col) {\nfprintf(stderr, \"Fehler i Ziile %d, Spalte %d: s Resultat vo `%s` \
+    passt nöd in %s\\n\", row, col, op, type);\nexit(EXIT_FAILURE);\n}\n";
+
+/// Name of the prelude function doing `op` on integers of type `pt`
+fn helper_name(op: BinOperator, pt: PrimType) -> String {
+    format!("haessig_{}_{}", op, pt.name_suffix())
+}
+
+/// Prelude function doing `op` on integers of type `pt`, it takes the operands and the row and
+/// column of the operation for error messages
+fn helper(op: BinOperator, pt: PrimType, overflow: Overflow) -> String {
+    let name = helper_name(op, pt);
+    let t = pt.c_type();
+    let signed = pt.is_signed();
+    // `uint8_t` -> `UINT8`
+    let limit = t.trim_end_matches("_t").to_uppercase();
+    let min = if signed {
+        format!("{limit}_MIN")
+    } else {
+        "0".to_string()
+    };
+    // whether the result left the range upwards
+    let up = match (op, signed) {
+        (BinOperator::Plus, true) => "b > 0",
+        (BinOperator::Minus, true) => "b < 0",
+        (BinOperator::Mal, true) => "(a < 0) == (b < 0)",
+        (BinOperator::Hoch, true) => "!neg",
+        (BinOperator::Minus, false) => "0",
+        _ => "1",
+    };
+    // `r = x * y` for `hoch`, which stops at the first overflow
+    let checked = |builtin: &str, x: &str, y: &str, r: &str| match overflow {
+        Overflow::Wrap => format!("__builtin_{builtin}_overflow({x}, {y}, &{r});"),
+        Overflow::Trap => format!(
+            "if (__builtin_{builtin}_overflow({x}, {y}, &{r})) {{\n\
+             haessig_overflow(\"{op}\", \"{pt}\", row, col);\n}}"
+        ),
+        Overflow::Saturate => format!(
+            "if (__builtin_{builtin}_overflow({x}, {y}, &{r})) {{\n\
+             return {up} ? {limit}_MAX : {min};\n}}"
+        ),
+    };
+    let body = match op {
+        // division by zero has no result in any mode, `MIN durch -1` is the only overflow
+        BinOperator::Durch | BinOperator::Rescht => {
+            let overflow = match (op, overflow) {
+                (BinOperator::Rescht, _) => "return 0;".to_string(),
+                (_, Overflow::Wrap) => format!("return {min};"),
+                (_, Overflow::Trap) => format!("haessig_overflow(\"{op}\", \"{pt}\", row, col);"),
+                (_, Overflow::Saturate) => format!("return {limit}_MAX;"),
+            };
+            let overflow = if signed {
+                format!("if (a == {min} && b == -1) {{\n{overflow}\n}}\n")
+            } else {
+                "".to_string()
+            };
+            let c_op = op.c_op();
+            format!(
+                "if (b == 0) {{\nhaessig_durch_null(row, col);\n}}\n{overflow}return a {c_op} b;"
+            )
+        }
+        BinOperator::Hoch => {
+            // only 1 and -1 have integer results for negative exponents
+            let neg = if signed {
+                "if (b < 0) {\nreturn a == 1 || a == -1 ? (b % 2 == 0 ? 1 : a) : 0;\n}\n\
+                 int neg = a < 0 && b % 2 != 0;\n(void)neg;\n"
+            } else {
+                ""
+            };
+            format!(
+                "{neg}{t} r = 1;\nwhile (b > 0) {{\nif (b & 1) {{\n{}\n}}\nb >>= 1;\n\
+                 if (b > 0) {{\n{}\n}}\n}}\nreturn r;",
+                checked("mul", "r", "a", "r"),
+                checked("mul", "a", "a", "a"),
+            )
+        }
+        _ => {
+            let builtin = match op {
+                BinOperator::Plus => "add",
+                BinOperator::Minus => "sub",
+                _ => "mul",
+            };
+            format!("{t} r;\n{}\nreturn r;", checked(builtin, "a", "b", "r"))
+        }
+    };
+    format!(
+        "static {t} {name}({t} a, {t} b, int row, int col) {{\n(void)row;\n(void)col;\n{body}\n}}\n"
+    )
+}
+
 pub struct Transpiler<'a> {
-    ast: &'a Program<'a>,
+    program: &'a TypedProgram<'a>,
+    overflow: Overflow,
+    /// what `gib` leaves, `None` for the function, else the number of the block used as a value
+    ret_targets: Vec<Option<usize>>,
+    /// number of blocks used as values so far, their labels and variables are numbered
+    block_values: usize,
 }
 
 impl<'a> Transpiler<'a> {
-    pub fn new(ast: &'a Program<'a>) -> Self {
-        Self { ast }
+    pub fn new(program: &'a TypedProgram<'a>) -> Self {
+        Self {
+            program,
+            overflow: Overflow::default(),
+            ret_targets: vec![],
+            block_values: 0,
+        }
     }
 
-    pub fn generate(&self) -> String {
-        let mut tl = "".to_string();
-        let mut main = "".to_string();
-        for stmt in self.ast.iter() {
-            match stmt {
-                Stmt::FunAss(fun_ass) => {
-                    tl += &fun_ass.transpile();
-                    tl += "\n"
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// C name of the variable or function `id` declared by `decl`. Every declaration gets its
+    /// own name ending in `__<decl>`, so shadowed variables and names of C and its library don't
+    /// clash. `-` and `'` aren't allowed in C names and become `_`.
+    fn c_name(&self, id: &str, decl: Option<DeclId>) -> String {
+        match decl {
+            Some(decl) => {
+                format!("{}__{}", self.program.decl(decl).id, decl).replace(['-', '\''], "_")
+            }
+            None => id.to_string(),
+        }
+    }
+
+    /// C name of the function `id` declared by `decl`, `chuchichäschtli` is the entry point
+    fn fun_name(&self, id: &str, decl: Option<DeclId>) -> String {
+        match id {
+            "chuchichäschtli" => "main".to_string(),
+            _ => self.c_name(id, decl),
+        }
+    }
+
+    /// Functions for the checked operations, conversions and loop steps `code` uses
+    fn prelude(&self, code: &str) -> String {
+        let used = |name: &str| code.contains(&(name.to_string() + "("));
+        let steps = if used("haessig_schritt") {
+            STEP_ERROR
+        } else {
+            ""
+        };
+        let mut conversions = "".to_string();
+        for name in ["haessig_zeiche_n", "haessig_zeiche_z", "haessig_zeiche_r"] {
+            if used(name) {
+                conversions += &to_zeiche(name);
+            }
+        }
+        let mut checked = "".to_string();
+        for pt in INT_TYPES {
+            if used(&from_float_name(pt)) {
+                conversions += &from_float(pt);
+            }
+            for op in [
+                BinOperator::Plus,
+                BinOperator::Minus,
+                BinOperator::Mal,
+                BinOperator::Hoch,
+                BinOperator::Durch,
+                BinOperator::Rescht,
+            ] {
+                if used(&helper_name(op, pt)) {
+                    checked += &helper(op, pt, self.overflow);
                 }
+            }
+        }
+        if checked.contains("haessig_overflow(") {
+            checked = OVERFLOW_ERROR.to_string() + &checked;
+        }
+        if checked.contains("haessig_durch_null(") {
+            checked = DIVISION_ERROR.to_string() + &checked;
+        }
+        steps.to_string() + &conversions + &checked
+    }
+
+    pub fn generate(&mut self) -> String {
+        let ast = self.program.ast();
+        // file scope initializers have to be constant, so top level variables are only declared
+        // there and set when the program starts, along with running the top level statements
+        let mut globals = "".to_string();
+        let mut init = "".to_string();
+        for stmt in ast.iter() {
+            match stmt {
+                Stmt::FunAss(_) => (),
                 Stmt::VarAss(var_ass) => {
-                    tl += &var_ass.transpile();
-                    tl += ";\n"
-                }
-                Stmt::StEx(st_ex) => main += &st_ex.transpile(),
-                Stmt::Ret(ret) => {
-                    main += &ret.transpile();
-                    main += ";\n"
+                    let id = self.c_name(var_ass.id, var_ass.decl.get());
+                    globals += &format!("{} {id};\n", var_ass.c_type());
+                    init += &format!("{id} = {};\n", var_ass.value.transpile(self));
                 }
+                // the other top level statements run in between, in order
+                _ => init += &stmt.transpile(self),
             }
         }
-        // format!("#include <stdio.h>\n{tl}\n{main}")
-        format!("#include <stdio.h>\n{tl}")
+        // forward prototypes, so functions can be called before they're defined
+        let mut protos = "".to_string();
+        let mut tl = "".to_string();
+        let mut has_main = false;
+        for stmt in ast.iter() {
+            let Stmt::FunAss(fun_ass) = stmt else {
+                continue;
+            };
+            if fun_ass.id == "chuchichäschtli" {
+                has_main = true;
+                tl += &fun_ass.transpile_with(self, &init);
+            } else {
+                protos += &fun_ass.signature(self);
+                protos += ";\n";
+                tl += &fun_ass.transpile(self);
+            }
+            tl += "\n"
+        }
+        if !has_main && !init.is_empty() {
+            tl += &format!("int main() {{\n{init}return 0;\n}}\n");
+        }
+        let prelude = self.prelude(&tl);
+        format!(
+            "#include <math.h>\n#include <stdint.h>\n#include <stdio.h>\n#include <stdlib.h>\n\
+             #include <string.h>\n{prelude}{globals}{protos}{tl}"
+        )
     }
 }
@@ -0,0 +1,145 @@
+use haessig::{
+    diag::{Diagnostic, Renderer},
+    lexer::Lexer,
+    parser::Parser,
+    seman::SemanticAnalyzer,
+};
+
+fn render(input: &str) -> String {
+    let renderer = Renderer::new(input, "test.hä");
+    let (toks, errs) = Lexer::new(input).lex();
+    if let Some(err) = errs.first() {
+        return renderer.render(&Diagnostic::from(err));
+    }
+    let ast = match Parser::new(&toks).parse() {
+        Ok(ast) => ast,
+        Err(errs) => return renderer.render(&Diagnostic::from(&errs[0])),
+    };
+    let errs = SemanticAnalyzer::new(&ast)
+        .analyze()
+        .expect_err("expected an error");
+    renderer.render(&Diagnostic::from(&errs[0]))
+}
+
+#[test]
+fn secondary_label_and_help() {
+    assert_eq!(
+        render("dä x isch 1;\nx wird 2;"),
+        "\
+error: Can't assign to `x` as it isn't declared with `dä veränderlich`
+ --> test.hä:2:1
+  |
+1 | dä x isch 1;
+  | ----------- declared immutable here
+2 | x wird 2;
+  | ^^^^^^^^ assigned here
+  |
+  = help: declare it with `dä veränderlich x isch ...;`
+"
+    );
+}
+
+#[test]
+fn skipped_lines() {
+    assert_eq!(
+        render("funktion f het N8 a { };\n\n\ntuen f mit wahr;"),
+        "\
+error: Argument `a` of function `f` is N8 but got Wahrheit
+ --> test.hä:4:12
+  |
+1 | funktion f het N8 a { };
+  |                ---- argument declared here
+...
+4 | tuen f mit wahr;
+  |            ^^^^ expected N8
+"
+    );
+}
+
+#[test]
+fn parse_error_note() {
+    assert_eq!(
+        render("dä x isch 1 plus;"),
+        "\
+error: Expected a value, got Semicolon
+ --> test.hä:1:17
+  |
+1 | dä x isch 1 plus;
+  |                 ^ expected a value
+  |
+  = note: while parsing Prim
+"
+    );
+}
+
+#[test]
+fn end_of_input() {
+    assert_eq!(
+        render("dä x isch (1"),
+        "\
+error: Unexpected end of input
+ --> test.hä:1:13
+  |
+1 | dä x isch (1
+  |             ^ expected more code here
+"
+    );
+}
+
+#[test]
+fn multi_line_span() {
+    assert_eq!(
+        render("dä x isch {\n    dä y isch 1;\n};"),
+        "\
+error: Block used as a value doesn't `gib` one
+ --> test.hä:1:11
+  |
+1 | dä x isch {
+  |           ^
+2 |     dä y isch 1;
+  |     ^^^^^^^^^^^^
+3 | };
+  | ^ doesn't `gib` a value
+"
+    );
+}
+
+#[test]
+fn lex_error() {
+    assert_eq!(
+        render("dä x isch 1 + 2;"),
+        "\
+error: Unknown character `+`
+ --> test.hä:1:13
+  |
+1 | dä x isch 1 + 2;
+  |             ^ not part of the language
+  |
+  = help: write `plus` instead
+"
+    );
+}
+
+#[test]
+fn var_ass_als_help() {
+    assert_eq!(
+        render("dä a isch 1;\ndä x isch a als Zeiche;"),
+        "\
+error: Can't assign value of type N8 to `x` declared `als` Zeiche
+ --> test.hä:2:11
+  |
+2 | dä x isch a als Zeiche;
+  |           ^ expected Zeiche
+  |
+  = help: convert the value with `dä x isch (... als Zeiche);`
+"
+    );
+}
+
+#[test]
+fn colour() {
+    let out = Renderer::new("x", "test.hä")
+        .colour(true)
+        .render(&Diagnostic::error("oops"));
+    assert_eq!(out, "\x1b[1;31merror\x1b[0m: \x1b[1moops\x1b[0m\n");
+}
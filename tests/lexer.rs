@@ -1,4 +1,18 @@
-use haessig::lexer::{Lexer, Token,  TT};
+use std::borrow::Cow;
+
+use haessig::lexer::{LexError, LexErrorKind, Lexer, Pos, Span, Token, TT};
+
+/// Span of `text` starting at the given position, `text` mustn't contain a line break
+fn span(row: usize, col: usize, offset: usize, text: &str) -> Span {
+    Span {
+        start: Pos { row, col, offset },
+        end: Pos {
+            row,
+            col: col + text.chars().count(),
+            offset: offset + text.len(),
+        },
+    }
+}
 
 #[test]
 fn var_ass() {
@@ -9,62 +23,396 @@ funktion test git Wahrheit {
 }
 "
     )
-    .lex();
+    .lex()
+    .0;
     let exp = vec![
         (Token {
             token_type: TT::Funktion,
             value: None,
-            row: 1,
-            col: 0,
+            span: span(1, 0, 1, "funktion"),
         }),
         (Token {
             token_type: TT::Id,
-            value: Some("test"),
-            row: 1,
-            col: 9,
+            value: Some("test".into()),
+            span: span(1, 9, 10, "test"),
         }),
         (Token {
             token_type: TT::Git,
             value: None,
-            row: 1,
-            col: 14,
+            span: span(1, 14, 15, "git"),
         }),
         (Token {
             token_type: TT::TypWahrheit,
             value: None,
-            row: 1,
-            col: 18,
+            span: span(1, 18, 19, "Wahrheit"),
         }),
         (Token {
             token_type: TT::LBrace,
             value: None,
-            row: 1,
-            col: 27,
+            span: span(1, 27, 28, "{"),
         }),
         (Token {
             token_type: TT::Gib,
             value: None,
-            row: 2,
-            col: 4,
+            span: span(2, 4, 34, "gib"),
         }),
         (Token {
             token_type: TT::Falsch,
             value: None,
-            row: 2,
-            col: 8,
+            span: span(2, 8, 38, "falsch"),
         }),
         (Token {
             token_type: TT::Semicolon,
             value: None,
-            row: 2,
-            col: 14,
+            span: span(2, 14, 44, ";"),
         }),
         (Token {
             token_type: TT::RBrace,
             value: None,
-            row: 3,
-            col: 0,
+            span: span(3, 0, 46, "}"),
         }),
     ];
     assert_eq!(res, exp);
 }
+
+#[test]
+fn parens() {
+    let res = Lexer::new("(x)plus(1)").lex().0;
+    let exp = vec![
+        (Token {
+            token_type: TT::LParen,
+            value: None,
+            span: span(0, 0, 0, "("),
+        }),
+        (Token {
+            token_type: TT::Id,
+            value: Some("x".into()),
+            span: span(0, 1, 1, "x"),
+        }),
+        (Token {
+            token_type: TT::RParen,
+            value: None,
+            span: span(0, 2, 2, ")"),
+        }),
+        (Token {
+            token_type: TT::Plus,
+            value: None,
+            span: span(0, 3, 3, "plus"),
+        }),
+        (Token {
+            token_type: TT::LParen,
+            value: None,
+            span: span(0, 7, 7, "("),
+        }),
+        (Token {
+            token_type: TT::Int,
+            value: Some("1".into()),
+            span: span(0, 8, 8, "1"),
+        }),
+        (Token {
+            token_type: TT::RParen,
+            value: None,
+            span: span(0, 9, 9, ")"),
+        }),
+    ];
+    assert_eq!(res, exp);
+}
+
+#[test]
+fn spans_count_chars_and_bytes() {
+    let res = Lexer::new("dä x;\n  für").lex().0;
+    let spans: Vec<Span> = res.iter().map(|t| t.span).collect();
+    assert_eq!(
+        spans,
+        vec![
+            span(0, 0, 0, "dä"),
+            span(0, 3, 4, "x"),
+            span(0, 4, 5, ";"),
+            span(1, 2, 9, "für"),
+        ]
+    );
+}
+
+#[test]
+fn errors() {
+    let (toks, errs) = Lexer::new("dä x isch 1 + 2;\ndä ü isch 1.2.3;\nx wird \"abc ;").lex();
+    assert_eq!(
+        errs,
+        vec![
+            LexError {
+                kind: LexErrorKind::UnknownChar('+'),
+                span: span(0, 12, 13, "+"),
+            },
+            LexError {
+                kind: LexErrorKind::MalformedNumber("1.2.3"),
+                span: span(1, 10, 30, "1.2.3"),
+            },
+            LexError {
+                kind: LexErrorKind::UnterminatedString,
+                span: span(2, 7, 44, "\"abc ;"),
+            },
+        ]
+    );
+    // lexing goes on after an error
+    let types: Vec<TT> = toks.iter().map(|t| t.token_type).collect();
+    assert_eq!(
+        types,
+        vec![
+            TT::Dä,
+            TT::Id,
+            TT::Isch,
+            TT::Int,
+            TT::Int,
+            TT::Semicolon,
+            TT::Dä,
+            TT::Id,
+            TT::Isch,
+            TT::Semicolon,
+            TT::Id,
+            TT::Wird,
+        ]
+    );
+}
+
+#[test]
+fn numbers() {
+    let (toks, errs) = Lexer::new("1 -2 0.5 12ab 3. ").lex();
+    let nums: Vec<_> = toks.iter().map(|t| t.value.as_deref()).collect();
+    assert_eq!(nums, vec![Some("1"), Some("-2"), Some("0.5")]);
+    let bad: Vec<_> = errs.iter().map(|e| e.kind.clone()).collect();
+    assert_eq!(
+        bad,
+        vec![
+            LexErrorKind::MalformedNumber("12ab"),
+            LexErrorKind::MalformedNumber("3."),
+        ]
+    );
+}
+
+#[test]
+fn number_kinds() {
+    let (toks, errs) = Lexer::new("1_000 0xFf_0a -0b10_1 1.5 6.02e23 1E-7 2e+1_0").lex();
+    assert!(errs.is_empty(), "{:?}", errs);
+    let nums: Vec<_> = toks
+        .iter()
+        .map(|t| (t.token_type, t.value.as_deref()))
+        .collect();
+    assert_eq!(
+        nums,
+        vec![
+            (TT::Int, Some("1_000")),
+            (TT::Int, Some("0xFf_0a")),
+            (TT::Int, Some("-0b10_1")),
+            (TT::Float, Some("1.5")),
+            (TT::Float, Some("6.02e23")),
+            (TT::Float, Some("1E-7")),
+            (TT::Float, Some("2e+1_0")),
+        ]
+    );
+    let (_, errs) = Lexer::new("1__0 2_ 0x 0b12 0x1.5 1e 1e-").lex();
+    let bad: Vec<_> = errs.iter().map(|e| e.kind.clone()).collect();
+    assert_eq!(
+        bad,
+        vec![
+            LexErrorKind::MalformedNumber("1__0"),
+            LexErrorKind::MalformedNumber("2_"),
+            LexErrorKind::MalformedNumber("0x"),
+            LexErrorKind::MalformedNumber("0b12"),
+            LexErrorKind::MalformedNumber("0x1.5"),
+            LexErrorKind::MalformedNumber("1e"),
+            LexErrorKind::MalformedNumber("1e-"),
+        ]
+    );
+}
+
+#[test]
+fn line_comments() {
+    let (toks, errs) = Lexer::new("// hoi\ndä x isch 1; // dä y isch 2;\nx// \"\n").lex();
+    assert!(errs.is_empty(), "{:?}", errs);
+    let types: Vec<TT> = toks.iter().map(|t| t.token_type).collect();
+    assert_eq!(
+        types,
+        vec![TT::Dä, TT::Id, TT::Isch, TT::Int, TT::Semicolon, TT::Id]
+    );
+    assert_eq!(toks[5].span, span(2, 0, 38, "x"));
+}
+
+#[test]
+fn block_comments() {
+    let (toks, errs) = Lexer::new("dä /* x */ y isch /* a\n /* nested\n */ b */ 1; /**/z").lex();
+    assert!(errs.is_empty(), "{:?}", errs);
+    let values: Vec<_> = toks
+        .iter()
+        .map(|t| (t.token_type, t.value.as_deref()))
+        .collect();
+    assert_eq!(
+        values,
+        vec![
+            (TT::Dä, None),
+            (TT::Id, Some("y")),
+            (TT::Isch, None),
+            (TT::Int, Some("1")),
+            (TT::Semicolon, None),
+            (TT::Id, Some("z")),
+        ]
+    );
+    assert_eq!(toks[3].span, span(2, 9, 44, "1"));
+}
+
+#[test]
+fn unterminated_block_comment() {
+    let (toks, errs) = Lexer::new("dä x isch 1;\n/* a /* b */\nfür").lex();
+    assert_eq!(toks.len(), 5);
+    assert_eq!(
+        errs,
+        vec![LexError {
+            kind: LexErrorKind::UnterminatedComment,
+            span: span(1, 0, 14, "/*"),
+        }]
+    );
+}
+
+fn types(input: &str) -> Vec<TT> {
+    let (toks, errs) = Lexer::new(input).lex();
+    assert!(errs.is_empty(), "{:?}", errs);
+    toks.iter().map(|t| t.token_type).collect()
+}
+
+#[test]
+fn keywords_at_eof_and_line_end() {
+    assert_eq!(types("falsch"), vec![TT::Falsch]);
+    assert_eq!(
+        types("dä x isch\nwahr\n;"),
+        vec![TT::Dä, TT::Id, TT::Isch, TT::Wahr, TT::Semicolon]
+    );
+    assert_eq!(types("hör uf"), vec![TT::HörUf]);
+    assert_eq!(types("nöd x"), vec![TT::Nöd, TT::Id]);
+    assert_eq!(
+        types("N8 N16 Z64 R4 N128"),
+        vec![TT::TypN8, TT::TypN16, TT::TypZ64, TT::TypR4, TT::Id]
+    );
+    // keywords don't span lines
+    assert_eq!(
+        types("x grösser\ngliich"),
+        vec![TT::Id, TT::Grösser, TT::Gliich]
+    );
+}
+
+#[test]
+fn keywords_before_separators() {
+    assert_eq!(
+        types("gib(wahr),falsch;{Zeiche}"),
+        vec![
+            TT::Gib,
+            TT::LParen,
+            TT::Wahr,
+            TT::RParen,
+            TT::Comma,
+            TT::Falsch,
+            TT::Semicolon,
+            TT::LBrace,
+            TT::TypZeiche,
+            TT::RBrace,
+        ]
+    );
+    assert_eq!(types("isch\"a\""), vec![TT::Isch, TT::Str]);
+    assert_eq!(types("wahr// c"), vec![TT::Wahr]);
+    assert_eq!(types("wahr/* c */falsch"), vec![TT::Wahr, TT::Falsch]);
+}
+
+#[test]
+fn keywords_maximal_munch() {
+    assert_eq!(types("grösser gliich"), vec![TT::GrösserGliich]);
+    assert_eq!(types("chlinner \t gliich"), vec![TT::ChlinnerGliich]);
+    assert_eq!(types("grösser gliichi"), vec![TT::Grösser, TT::Id]);
+    // keywords are only recognised as whole words
+    assert_eq!(
+        types("wahrheit däx isch_ gib2 mal'"),
+        vec![TT::Id, TT::Id, TT::Id, TT::Id, TT::Id]
+    );
+    let (toks, _) = Lexer::new("mach  wiiter;").lex();
+    assert_eq!(toks[0].span, span(0, 0, 0, "mach  wiiter"));
+}
+
+#[test]
+fn string_escapes() {
+    let (toks, errs) = Lexer::new(r#"dä s isch "a\"b\\c\nd\te \u{e4}\u{1F600} ü"; "rå""#).lex();
+    assert!(errs.is_empty(), "{:?}", errs);
+    assert_eq!(toks[3].value.as_deref(), Some("a\"b\\c\nd\te ä😀 ü"));
+    assert_eq!(
+        toks[3].span,
+        span(0, 10, 11, r#""a\"b\\c\nd\te \u{e4}\u{1F600} ü""#)
+    );
+    // strings without escapes aren't copied
+    assert!(matches!(toks[5].value, Some(Cow::Borrowed("rå"))));
+}
+
+#[test]
+fn invalid_escapes() {
+    let (toks, errs) = Lexer::new(r#""ä\q" "\u{110000}" "\u{}" "\u{0}" x"#).lex();
+    let escapes: Vec<_> = errs
+        .iter()
+        .map(|e| (e.kind.clone(), e.span.start.col))
+        .collect();
+    assert_eq!(
+        escapes,
+        vec![
+            (LexErrorKind::InvalidEscape(r"\q"), 2),
+            (LexErrorKind::InvalidEscape(r"\u{110000}"), 7),
+            (LexErrorKind::InvalidEscape(r"\u{}"), 20),
+            (LexErrorKind::InvalidEscape(r"\u{0}"), 27),
+        ]
+    );
+    // lexing goes on after the string
+    assert_eq!(toks.len(), 1);
+    assert_eq!(toks[0].value.as_deref(), Some("x"));
+}
+
+#[test]
+fn multi_line_strings() {
+    let input = "dä s isch \"\"\"\n  Grüezi \"Wält\"\\t\n\\u{e4}\"\"\";\nx";
+    let (toks, errs) = Lexer::new(input).lex();
+    assert!(errs.is_empty(), "{:?}", errs);
+    assert_eq!(toks[3].value.as_deref(), Some("  Grüezi \"Wält\"\t\nä"));
+    assert_eq!(
+        toks[3].span.start,
+        Pos {
+            row: 0,
+            col: 10,
+            offset: 11
+        }
+    );
+    assert_eq!(
+        toks[3].span.end,
+        Pos {
+            row: 2,
+            col: 9,
+            offset: 44
+        }
+    );
+    // tokens after the literal are still where they are
+    assert_eq!(toks[4].span, span(2, 9, 44, ";"));
+    assert_eq!(toks[5].span, span(3, 0, 46, "x"));
+}
+
+#[test]
+fn raw_strings() {
+    let (toks, errs) = Lexer::new("r\"C:\\neu\\\" r\"\"\"a\n\\\"b\"\"\" \"\"").lex();
+    assert!(errs.is_empty(), "{:?}", errs);
+    let values: Vec<_> = toks.iter().map(|t| t.value.as_deref()).collect();
+    assert_eq!(values, vec![Some("C:\\neu\\"), Some("a\n\\\"b"), Some("")]);
+    assert_eq!(toks[2].span, span(1, 7, 24, "\"\""));
+}
+
+#[test]
+fn unterminated_multi_line_string() {
+    let (toks, errs) = Lexer::new("dä s isch \"\"\"hoi\n\"\";\n").lex();
+    assert_eq!(toks.len(), 3);
+    assert_eq!(
+        errs,
+        vec![LexError {
+            kind: LexErrorKind::UnterminatedMultiLineString,
+            span: span(0, 10, 11, "\"\"\""),
+        }]
+    );
+}
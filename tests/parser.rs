@@ -1,50 +1,90 @@
-use haessig::{lexer::Lexer, parser::Parser};
+use haessig::{
+    diag::Diagnostic,
+    lexer::Lexer,
+    parser::{Else, Expr, ExprKind, Parser, Prim, PrimType, StEx, Stmt},
+};
+
+fn shape(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::Bin(bin) => format!("({:?} {} {})", bin.op, shape(&bin.lhs), shape(&bin.rhs)),
+        ExprKind::Prim(Prim::Id(id, _)) => id.to_string(),
+        ExprKind::Prim(Prim::Int(n)) => n.to_string(),
+        ExprKind::Prim(Prim::Float(n)) => format!("{:?}", n),
+        ExprKind::Prim(Prim::Bool(b)) => b.to_string(),
+        ExprKind::Un(un) => format!("({} {})", un.op, shape(&un.expr)),
+        ExprKind::Cast(cast) => format!("(als {} {})", shape(&cast.expr), cast.pt),
+        ExprKind::Cond(cond) => format!(
+            "(wenn {} {} {})",
+            shape(&cond.cond),
+            shape(&cond.then),
+            shape(&cond.els)
+        ),
+        ExprKind::StEx(StEx::Call(call)) => format!(
+            "(tuen {}{})",
+            call.id,
+            call.args
+                .iter()
+                .map(|a| format!(" {}", shape(a)))
+                .collect::<String>()
+        ),
+        e => format!("{:?}", e),
+    }
+}
+
+fn expr_shape(input: &str) -> String {
+    let toks = Lexer::new(input).lex().0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    match ast.first() {
+        Some(Stmt::VarAss(var_ass)) => shape(&var_ass.value),
+        s => panic!("expected VarAss, got {:?}", s),
+    }
+}
 
 #[test]
 fn var_ass() {
-    assert!(Parser::new(&Lexer::new("dä x isch 5;").lex())
+    assert!(Parser::new(&Lexer::new("dä x isch 5;").lex().0)
         .parse()
         .is_ok());
 }
 
 #[test]
 fn var_ass_str() {
-    assert!(Parser::new(&Lexer::new("dä x isch \"5\";").lex())
+    assert!(Parser::new(&Lexer::new("dä x isch \"5\";").lex().0)
         .parse()
         .is_ok());
 }
 
 #[test]
 fn var_ass_bin() {
-    assert!(Parser::new(&Lexer::new("dä x isch 5 plus 5 minus 7;").lex())
+    assert!(Parser::new(&Lexer::new("dä x isch 5 plus 5 minus 7;").lex().0)
         .parse()
         .is_ok());
 }
 
 #[test]
 fn var_ass_bool() {
-    assert!(Parser::new(&Lexer::new("dä x isch wahr;").lex())
+    assert!(Parser::new(&Lexer::new("dä x isch wahr;").lex().0)
         .parse()
         .is_ok());
 }
 
 #[test]
 fn block() {
-    assert!(Parser::new(&Lexer::new("{dä x isch 5;};").lex())
+    assert!(Parser::new(&Lexer::new("{dä x isch 5;};").lex().0)
         .parse()
         .is_ok());
 }
 
 #[test]
 fn fun_ass() {
-    assert!(Parser::new(&Lexer::new("funktion f {};").lex())
+    assert!(Parser::new(&Lexer::new("funktion f {};").lex().0)
         .parse()
         .is_ok());
 }
 
 #[test]
 fn fun_ass_with_ret() {
-    assert!(Parser::new(&Lexer::new("funktion f git N8 {};").lex())
+    assert!(Parser::new(&Lexer::new("funktion f git N8 {};").lex().0)
         .parse()
         .is_ok());
 }
@@ -52,7 +92,7 @@ fn fun_ass_with_ret() {
 #[test]
 fn fun_ass_with_args() {
     assert!(
-        Parser::new(&Lexer::new("funktion f het N8 x, N8 y {};").lex())
+        Parser::new(&Lexer::new("funktion f het N8 x, N8 y {};").lex().0)
             .parse()
             .is_ok()
     );
@@ -61,7 +101,7 @@ fn fun_ass_with_args() {
 #[test]
 fn fun_ass_with_args_and_ret() {
     assert!(
-        Parser::new(&Lexer::new("funktion f het N8 x, N8 y git N8 {};").lex())
+        Parser::new(&Lexer::new("funktion f het N8 x, N8 y git N8 {};").lex().0)
             .parse()
             .is_ok()
     );
@@ -70,7 +110,7 @@ fn fun_ass_with_args_and_ret() {
 #[test]
 fn call() {
     assert!(
-        Parser::new(&Lexer::new("tuen schreie mit 5;").lex())
+        Parser::new(&Lexer::new("tuen schreie mit 5;").lex().0)
             .parse()
             .is_ok()
     );
@@ -79,7 +119,7 @@ fn call() {
 #[test]
 fn call_block() {
     assert!(
-        Parser::new(&Lexer::new("tuen schreie mit { gib 5; };").lex())
+        Parser::new(&Lexer::new("tuen schreie mit { gib 5; };").lex().0)
             .parse()
             .is_ok()
     );
@@ -88,8 +128,442 @@ fn call_block() {
 #[test]
 fn call_bin() {
     assert!(
-        Parser::new(&Lexer::new("tuen schreie mit 99 plus { gib 5; };").lex())
+        Parser::new(&Lexer::new("tuen schreie mit 99 plus { gib 5; };").lex().0)
             .parse()
             .is_ok()
     );
 }
+
+#[test]
+fn prec_left_assoc() {
+    assert_eq!(
+        expr_shape("dä r isch x minus y plus z;"),
+        "(Plus (Minus x y) z)"
+    );
+    assert_eq!(
+        expr_shape("dä r isch x durch y mal z rescht w;"),
+        "(Rescht (Mal (Durch x y) z) w)"
+    );
+}
+
+#[test]
+fn prec_mul_over_add() {
+    assert_eq!(
+        expr_shape("dä r isch a mal b plus c;"),
+        "(Plus (Mal a b) c)"
+    );
+    assert_eq!(
+        expr_shape("dä r isch a plus b mal c;"),
+        "(Plus a (Mal b c))"
+    );
+}
+
+#[test]
+fn prec_hoch_right_assoc() {
+    assert_eq!(
+        expr_shape("dä r isch a hoch b hoch c;"),
+        "(Hoch a (Hoch b c))"
+    );
+    assert_eq!(
+        expr_shape("dä r isch a mal b hoch c;"),
+        "(Mal a (Hoch b c))"
+    );
+}
+
+#[test]
+fn prec_cmp_over_bool() {
+    assert_eq!(
+        expr_shape("dä r isch a plus 1 chlinner b und c gliich d oder wahr;"),
+        "(Oder (Und (Chlinner (Plus a 1) b) (Gliich c d)) true)"
+    );
+    assert_eq!(
+        expr_shape("dä r isch a oder b und c;"),
+        "(Oder a (Und b c))"
+    );
+}
+
+#[test]
+fn prec_multi_word_ops() {
+    assert_eq!(
+        expr_shape("dä r isch a grösser gliich b mal 2 und a chlinner gliich 9;"),
+        "(Und (GrösserGliich a (Mal b 2)) (ChlinnerGliich a 9))"
+    );
+}
+
+#[test]
+fn paren_overrides_prec() {
+    assert_eq!(
+        expr_shape("dä r isch x minus (y plus z);"),
+        "(Minus x (Plus y z))"
+    );
+    assert_eq!(
+        expr_shape("dä r isch (a plus b) mal c;"),
+        "(Mal (Plus a b) c)"
+    );
+    assert_eq!(
+        expr_shape("dä r isch ((a hoch b)) hoch c;"),
+        "(Hoch (Hoch a b) c)"
+    );
+}
+
+#[test]
+fn paren_nested_call_args() {
+    assert_eq!(
+        expr_shape("dä r isch tuen f mit (tuen g mit 1, 2), 3;"),
+        "(tuen f (tuen g 1 2) 3)"
+    );
+    assert_eq!(
+        expr_shape("dä r isch (tuen f mit 1) plus 2;"),
+        "(Plus (tuen f 1) 2)"
+    );
+}
+
+#[test]
+fn paren_unclosed() {
+    assert!(Parser::new(&Lexer::new("dä r isch (1 plus 2;").lex().0)
+        .parse()
+        .is_err());
+}
+
+#[test]
+fn cond() {
+    assert_eq!(
+        expr_shape("dä r isch y mal x wenn z chlinner y suscht z minus x plus y;"),
+        "(wenn (Chlinner z y) (Mal y x) (Plus (Minus z x) y))"
+    );
+}
+
+#[test]
+fn cond_chained() {
+    assert_eq!(
+        expr_shape("dä r isch 1 wenn a suscht 2 wenn b suscht 3;"),
+        "(wenn a 1 (wenn b 2 3))"
+    );
+    assert_eq!(
+        expr_shape("dä r isch (1 wenn a suscht 2) plus 3;"),
+        "(Plus (wenn a 1 2) 3)"
+    );
+}
+
+#[test]
+fn cond_missing_suscht() {
+    assert!(Parser::new(&Lexer::new("dä r isch 1 wenn a;").lex().0)
+        .parse()
+        .is_err());
+}
+
+#[test]
+fn if_stmt() {
+    let toks = Lexer::new("wenn x chlinner 3 { tuen schreie mit x; };")
+        .lex()
+        .0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    let Some(Stmt::If(if_stmt)) = ast.first() else {
+        panic!("expected If, got {:?}", ast);
+    };
+    assert_eq!(shape(&if_stmt.cond), "(Chlinner x 3)");
+    assert_eq!(if_stmt.then.stmts.len(), 1);
+    assert!(if_stmt.els.is_none());
+}
+
+#[test]
+fn if_else_if_else() {
+    let toks =
+        Lexer::new("wenn a { gib 1; } suscht wenn b { gib 2; } suscht { gib 3; dä x isch 4; };")
+            .lex()
+            .0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    let Some(Stmt::If(if_stmt)) = ast.first() else {
+        panic!("expected If, got {:?}", ast);
+    };
+    let Some(Else::If(else_if)) = &if_stmt.els else {
+        panic!("expected else if, got {:?}", if_stmt.els);
+    };
+    assert_eq!(shape(&else_if.cond), "b");
+    let Some(Else::Block(els)) = &else_if.els else {
+        panic!("expected else, got {:?}", else_if.els);
+    };
+    assert_eq!(els.stmts.len(), 2);
+}
+
+#[test]
+fn if_call_without_args() {
+    let toks =
+        Lexer::new("wenn tuen ja mit { gib 1; } suscht wenn tuen f mit (tuen g mit { }) { };")
+            .lex()
+            .0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    let Some(Stmt::If(if_stmt)) = ast.first() else {
+        panic!("expected If, got {:?}", ast);
+    };
+    assert_eq!(shape(&if_stmt.cond), "(tuen ja)");
+    assert_eq!(if_stmt.then.stmts.len(), 1);
+    let Some(Else::If(else_if)) = &if_stmt.els else {
+        panic!("expected else if, got {:?}", if_stmt.els);
+    };
+    // in parentheses a block is an argument again
+    let ExprKind::StEx(StEx::Call(call)) = &else_if.cond.kind else {
+        panic!("expected Call, got {:?}", else_if.cond);
+    };
+    assert!(matches!(&call.args[0].kind, ExprKind::StEx(StEx::Call(g)) if g.args.len() == 1));
+}
+
+#[test]
+fn if_without_block() {
+    assert!(Parser::new(&Lexer::new("wenn a gib 1;;").lex().0)
+        .parse()
+        .is_err());
+    assert!(
+        Parser::new(&Lexer::new("wenn a { } suscht gib 1;;").lex().0)
+            .parse()
+            .is_err()
+    );
+}
+
+#[test]
+fn while_stmt() {
+    let toks = Lexer::new("solang i chlinner 10 { wenn i gliich 5 { hör uf; }; mach wiiter; };")
+        .lex()
+        .0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    let Some(Stmt::While(while_stmt)) = ast.first() else {
+        panic!("expected While, got {:?}", ast);
+    };
+    assert_eq!(shape(&while_stmt.cond), "(Chlinner i 10)");
+    let [Stmt::If(if_stmt), Stmt::Continue(_)] = &while_stmt.body.stmts[..] else {
+        panic!("unexpected loop body {:?}", while_stmt.body);
+    };
+    assert!(matches!(&if_stmt.then.stmts[..], [Stmt::Break(_)]));
+}
+
+#[test]
+fn while_call_without_args() {
+    let toks = Lexer::new("solang tuen wiiter mit { hör uf; };").lex().0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    let Some(Stmt::While(while_stmt)) = ast.first() else {
+        panic!("expected While, got {:?}", ast);
+    };
+    assert_eq!(shape(&while_stmt.cond), "(tuen wiiter)");
+    assert!(matches!(&while_stmt.body.stmts[..], [Stmt::Break(_)]));
+}
+
+#[test]
+fn while_without_block() {
+    assert!(Parser::new(&Lexer::new("solang wahr hör uf;;").lex().0)
+        .parse()
+        .is_err());
+}
+
+#[test]
+fn for_stmt() {
+    let toks = Lexer::new("für i vo 0 bis n mal 2 schritt 2 { tuen schreie mit i; };")
+        .lex()
+        .0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    let Some(Stmt::For(for_stmt)) = ast.first() else {
+        panic!("expected For, got {:?}", ast);
+    };
+    assert_eq!(for_stmt.id, "i");
+    assert_eq!(shape(&for_stmt.from), "0");
+    assert_eq!(shape(&for_stmt.to), "(Mal n 2)");
+    assert_eq!(for_stmt.step.as_ref().map(shape).as_deref(), Some("2"));
+    assert_eq!(for_stmt.body.stmts.len(), 1);
+}
+
+#[test]
+fn for_without_step() {
+    let toks = Lexer::new("für i vo 0 bis 10 { };").lex().0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    let Some(Stmt::For(for_stmt)) = ast.first() else {
+        panic!("expected For, got {:?}", ast);
+    };
+    assert!(for_stmt.step.is_none());
+}
+
+#[test]
+fn for_call_without_args() {
+    let toks =
+        Lexer::new("für i vo 0 bis tuen n mit { }; für j vo 0 bis 9 schritt tuen s mit { };")
+            .lex()
+            .0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    let [Stmt::For(for_bis), Stmt::For(for_schritt)] = &ast[..] else {
+        panic!("expected two For, got {:?}", ast);
+    };
+    assert_eq!(shape(&for_bis.to), "(tuen n)");
+    assert_eq!(
+        for_schritt.step.as_ref().map(shape).as_deref(),
+        Some("(tuen s)")
+    );
+}
+
+#[test]
+fn for_missing_bis() {
+    assert!(Parser::new(&Lexer::new("für i vo 0 { };").lex().0)
+        .parse()
+        .is_err());
+}
+
+#[test]
+fn reass() {
+    let toks = Lexer::new("x wird x plus 1;").lex().0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    let Some(Stmt::Reass(reass)) = ast.first() else {
+        panic!("expected Reass, got {:?}", ast);
+    };
+    assert_eq!(reass.id, "x");
+    assert_eq!(shape(&reass.value), "(Plus x 1)");
+}
+
+#[test]
+fn reass_missing_wird() {
+    assert!(Parser::new(&Lexer::new("x isch 1;").lex().0)
+        .parse()
+        .is_err());
+}
+
+#[test]
+fn var_ass_mutable() {
+    let toks = Lexer::new("dä x isch 1; dä veränderlich y isch 2;").lex().0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    let [Stmt::VarAss(x), Stmt::VarAss(y)] = &ast[..] else {
+        panic!("expected two VarAss, got {:?}", ast);
+    };
+    assert!(!x.mutable);
+    assert_eq!(y.id, "y");
+    assert!(y.mutable);
+}
+
+#[test]
+fn spans() {
+    let toks = Lexer::new("dä x isch (1 plus 2) mal y;\nhör uf;").lex().0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    let Stmt::VarAss(var_ass) = &ast[0] else {
+        panic!("expected VarAss");
+    };
+    // the trailing `;` isn't part of the statement
+    assert_eq!((var_ass.span.start.col, var_ass.span.end.col), (0, 26));
+    assert_eq!(
+        (var_ass.value.span.start.col, var_ass.value.span.end.col),
+        (10, 26)
+    );
+    let ExprKind::Bin(bin) = &var_ass.value.kind else {
+        panic!("expected Bin");
+    };
+    // parentheses are part of the sub-expression
+    assert_eq!((bin.lhs.span.start.col, bin.lhs.span.end.col), (10, 20));
+    assert_eq!(ast[1].span().start.row, 1);
+    assert_eq!(ast[1].span().end.col, 6);
+}
+
+#[test]
+fn number_literals() {
+    assert_eq!(expr_shape("dä x isch 1_000;"), "1000");
+    assert_eq!(expr_shape("dä x isch -0xff;"), "-255");
+    assert_eq!(expr_shape("dä x isch 0b1010;"), "10");
+    assert_eq!(
+        expr_shape("dä x isch 18446744073709551615;"),
+        "18446744073709551615"
+    );
+    assert_eq!(expr_shape("dä x isch 3.0;"), "3.0");
+    assert_eq!(expr_shape("dä x isch 2.5e-3;"), "0.0025");
+    let toks = Lexer::new("dä x isch 0x1_0000_0000_0000_0000_0000_0000_0000_0000;")
+        .lex()
+        .0;
+    assert!(Parser::new(&toks).parse().is_err());
+}
+
+#[test]
+fn cast() {
+    assert_eq!(
+        expr_shape("dä x isch x als R8 durch 2;"),
+        "(Durch (als x R8) 2)"
+    );
+    assert_eq!(
+        expr_shape("dä x isch (2 hoch (a plus 1) als N16 als Zeiche);"),
+        "(Hoch 2 (als (als (Plus a 1) N16) Zeiche))"
+    );
+    // the last argument of a call is cast, not the call
+    assert_eq!(
+        expr_shape("dä x isch (tuen f mit a als Z8);"),
+        "(tuen f (als a Z8))"
+    );
+    assert!(Parser::new(&Lexer::new("dä x isch a als b;").lex().0)
+        .parse()
+        .is_err());
+}
+
+#[test]
+fn var_ass_als_is_not_a_cast() {
+    let toks = Lexer::new("dä x isch a plus 1 als R8; dä y isch (a als R8);")
+        .lex()
+        .0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    let Some(Stmt::VarAss(x)) = ast.first() else {
+        panic!("expected VarAss, got {:?}", ast.first());
+    };
+    assert_eq!(x.pt, Some(PrimType::R8));
+    assert_eq!(shape(&x.value), "(Plus a 1)");
+    let Some(Stmt::VarAss(y)) = ast.get(1) else {
+        panic!("expected VarAss, got {:?}", ast.get(1));
+    };
+    assert_eq!(y.pt, None);
+    assert_eq!(shape(&y.value), "(als a R8)");
+}
+
+#[test]
+fn recovers_after_errors() {
+    let toks = Lexer::new(
+        "dä x isch ;\nfunktion f { dä a isch 1 2; gib 1; x wird; };\n}; dä y isch 1;\ndä z isch (1;",
+    )
+    .lex().0;
+    let errs = Parser::new(&toks).parse().expect_err("expected errors");
+    let rows: Vec<_> = errs
+        .iter()
+        .map(|e| Diagnostic::from(e).labels[0].span.start.row)
+        .collect();
+    assert_eq!(rows, [0, 1, 1, 2, 3]);
+}
+
+#[test]
+fn max_errors() {
+    let toks = Lexer::new("dä x isch ; dä y isch ; dä z isch ;").lex().0;
+    assert_eq!(
+        Parser::new(&toks).parse().map_err(|e| e.len()).err(),
+        Some(3)
+    );
+    assert_eq!(
+        Parser::new(&toks)
+            .max_errors(2)
+            .parse()
+            .map_err(|e| e.len())
+            .err(),
+        Some(2)
+    );
+}
+
+#[test]
+fn un() {
+    assert_eq!(expr_shape("dä x isch minus a;"), "(minus a)");
+    assert_eq!(expr_shape("dä x isch nöd nöd a;"), "(nöd (nöd a))");
+    // minus binds tighter than mal but looser than hoch
+    assert_eq!(expr_shape("dä x isch minus a mal b;"), "(Mal (minus a) b)");
+    assert_eq!(
+        expr_shape("dä x isch minus 2 hoch 2;"),
+        "(minus (Hoch 2 2))"
+    );
+    assert_eq!(
+        expr_shape("dä x isch a plus minus b;"),
+        "(Plus a (minus b))"
+    );
+    // nöd covers comparisons but not und
+    assert_eq!(
+        expr_shape("dä x isch nöd a gliich b;"),
+        "(nöd (Gliich a b))"
+    );
+    assert_eq!(expr_shape("dä x isch nöd a und b;"), "(Und (nöd a) b)");
+    assert_eq!(
+        expr_shape("dä x isch (minus a als R8);"),
+        "(minus (als a R8))"
+    );
+}
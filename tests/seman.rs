@@ -0,0 +1,482 @@
+use haessig::{
+    lexer::Lexer,
+    parser::{ExprKind, Parser, Prim, PrimType, StEx, Stmt},
+    seman::{SemanticAnalyzer, Symbol},
+};
+
+fn join<E: ToString>(errs: Vec<E>) -> String {
+    errs.iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn analyze(input: &str) -> Result<(), String> {
+    let toks = Lexer::new(input).lex().0;
+    let ast = Parser::new(&toks).parse().map_err(join)?;
+    let res = SemanticAnalyzer::new(&ast)
+        .analyze()
+        .map(|_| ())
+        .map_err(join);
+    res
+}
+
+#[test]
+fn cond() {
+    assert!(
+        analyze("funktion f het N8 a, N8 b git N8 { gib a wenn a grösser b suscht b; };").is_ok()
+    );
+}
+
+#[test]
+fn cond_not_boolean() {
+    assert!(analyze("funktion f het N8 a, N8 b git N8 { gib a wenn a suscht b; };").is_err());
+}
+
+#[test]
+fn cond_branch_mismatch() {
+    assert!(
+        analyze("funktion f het N8 a, Zeiche s git N8 { gib a wenn wahr suscht s; };").is_err()
+    );
+}
+
+#[test]
+fn if_stmt() {
+    assert!(analyze(
+        "funktion f het N8 a git N8 { wenn a grösser 2 { gib 1; } suscht wenn wahr { gib 2; }; gib 3; };"
+    )
+    .is_ok());
+}
+
+#[test]
+fn if_not_boolean() {
+    assert!(analyze("funktion f het Zeiche s { wenn s { }; };").is_err());
+    assert!(analyze("funktion f het N8 a { wenn wahr { } suscht wenn a { }; };").is_err());
+}
+
+#[test]
+fn while_stmt() {
+    assert!(analyze(
+        "funktion f het N8 a { solang a grösser 2 { wenn wahr { hör uf; }; mach wiiter; }; };"
+    )
+    .is_ok());
+}
+
+#[test]
+fn while_not_boolean() {
+    assert!(analyze("funktion f het Zeiche s { solang s { }; };").is_err());
+}
+
+#[test]
+fn break_continue_outside_loop() {
+    assert!(analyze("funktion f { hör uf; };").is_err());
+    assert!(analyze("funktion f { wenn wahr { mach wiiter; }; };").is_err());
+    assert!(analyze("funktion f { solang wahr { funktion g { hör uf; }; }; };").is_err());
+}
+
+#[test]
+fn for_stmt() {
+    assert!(analyze(
+        "funktion f het Z8 n { für i vo 0 bis n { wenn i gliich 2 { hör uf; }; tuen schreie mit i; }; };"
+    )
+    .is_ok());
+}
+
+#[test]
+fn for_var_scoped_to_body() {
+    assert!(analyze("funktion f { für i vo 0 bis 3 { }; tuen schreie mit i; };").is_err());
+}
+
+#[test]
+fn for_bound_not_integer() {
+    assert!(analyze("funktion f het Zeiche s { für i vo 0 bis s { }; };").is_err());
+    assert!(analyze("funktion f { für i vo 0 bis 3 schritt wahr { }; };").is_err());
+}
+
+#[test]
+fn for_step_not_positive() {
+    assert!(analyze("funktion f { für i vo 0 bis 3 schritt 0 { }; };").is_err());
+    assert_eq!(
+        analyze("funktion f { für j vo 10 als Z8 bis 0 schritt minus 1 { }; };"),
+        Err(
+            "Your code is semantically incorrect at row 1 col 47 :) Step of loop variable `j` must be greater than 0"
+                .to_string()
+        )
+    );
+    // other steps are checked at runtime
+    assert!(analyze("funktion f het Z8 s { für i vo 0 bis 3 schritt s { }; };").is_ok());
+}
+
+#[test]
+fn for_assign_loop_var() {
+    assert!(analyze("funktion f { für i vo 0 bis 3 { dä i isch 2; }; };").is_err());
+    assert!(analyze("funktion f { für i vo 0 bis 3 { wenn wahr { dä i isch 2; }; }; };").is_err());
+}
+
+#[test]
+fn reass() {
+    assert!(analyze("funktion f het N8 a { dä veränderlich x isch a; x wird x plus 1; };").is_ok());
+    assert!(analyze("funktion f { dä veränderlich s isch \"a\"; s wird \"b\"; };").is_ok());
+}
+
+#[test]
+fn reass_not_defined() {
+    assert!(analyze("funktion f { x wird 1; };").is_err());
+    assert!(analyze("funktion f { wenn wahr { dä veränderlich x isch 1; }; x wird 2; };").is_err());
+}
+
+#[test]
+fn reass_type_mismatch() {
+    assert!(analyze("funktion f het N8 a { dä veränderlich x isch a; x wird \"a\"; };").is_err());
+    assert!(
+        analyze("funktion f het Wahrheit a, N8 b { dä veränderlich x isch a; x wird b; };")
+            .is_err()
+    );
+}
+
+#[test]
+fn reass_loop_var() {
+    assert!(analyze("funktion f { für i vo 0 bis 3 { i wird 2; }; };").is_err());
+}
+
+#[test]
+fn assign_immutable() {
+    assert!(analyze("funktion f { dä x isch 1; x wird 2; };").is_err());
+    assert!(analyze("funktion f het N8 a { a wird 2; };").is_err());
+    assert!(analyze("funktion f { dä veränderlich x isch 1; dä x isch 2; x wird 3; };").is_err());
+    assert!(analyze("funktion f { dä x isch 1; dä veränderlich x isch 2; x wird 3; };").is_ok());
+}
+
+#[test]
+fn var_ass_als() {
+    assert!(analyze("dä x isch 5 als Z8; dä y isch x als Z8;").is_ok());
+    assert!(analyze("dä x isch 5 als N8; dä y isch x als Z8;").is_err());
+    assert!(analyze("dä x isch \"5\" als N8;").is_err());
+    assert!(analyze("dä x isch 5 als Wahrheit;").is_err());
+}
+
+#[test]
+fn var_ass_infers_type() {
+    assert!(analyze("dä x isch 5 als Z8; dä veränderlich y isch x; y wird y minus 1;").is_ok());
+    assert!(analyze("dä x isch wahr; dä veränderlich y isch x; y wird 1;").is_err());
+}
+
+#[test]
+fn bin_operand_types() {
+    assert!(analyze("funktion f het R8 a, R8 b git R8 { gib a durch b plus 1; };").is_ok());
+    assert!(analyze("funktion f het N8 a, Z8 b git N8 { gib a plus b; };").is_err());
+    assert!(analyze("funktion f het Zeiche a git Zeiche { gib a plus a; };").is_err());
+    assert!(analyze("funktion f het R8 a git R8 { gib a rescht 2; };").is_err());
+    assert!(analyze("funktion f het N8 a git Wahrheit { gib a und wahr; };").is_err());
+    assert!(analyze("funktion f het Zeiche a git Wahrheit { gib a grösser a; };").is_err());
+}
+
+#[test]
+fn bin_literals_adapt() {
+    assert!(analyze("funktion f het Z8 a git Z8 { gib 1 plus 2 mal a; };").is_ok());
+    assert!(analyze("funktion f het R8 a git Wahrheit { gib 1 chlinner a; };").is_ok());
+    assert!(analyze("funktion f het Zeiche a git Wahrheit { gib a gliich \"a\"; };").is_ok());
+    assert!(analyze("funktion f het Zeiche a git Wahrheit { gib a gliich 1; };").is_err());
+}
+
+#[test]
+fn call_args() {
+    assert!(analyze(
+        "funktion f het N8 a, Zeiche b git N8 { gib a; }; dä x isch tuen f mit 1, \"b\";"
+    )
+    .is_ok());
+    assert!(analyze("funktion f het N8 a { }; tuen f mit 1, 2;").is_err());
+    assert!(analyze("funktion f het N8 a, N8 b { }; tuen f mit 1;").is_err());
+    assert!(analyze("funktion f het N8 a { }; tuen f mit wahr;").is_err());
+}
+
+#[test]
+fn call_value() {
+    assert!(analyze("funktion f { }; dä x isch tuen f mit;").is_err());
+    assert!(analyze("dä f isch 1; tuen f mit 1;").is_err());
+    assert!(analyze("funktion f { }; dä x isch f;").is_err());
+}
+
+#[test]
+fn ret_types() {
+    assert!(analyze("funktion f git N8 { gib wahr; };").is_err());
+    assert!(analyze("funktion f { gib 1; };").is_err());
+    assert!(analyze("gib 1;").is_err());
+    assert!(
+        analyze("funktion f git Z8 { funktion g git Wahrheit { gib wahr; }; gib 1; };").is_ok()
+    );
+}
+
+#[test]
+fn block_value() {
+    assert!(analyze("dä x isch { gib 1; } als R8;").is_ok());
+    assert!(analyze("dä x isch { dä y isch 1; };").is_err());
+    assert!(analyze("dä x isch { wenn wahr { gib 1; }; gib wahr; };").is_err());
+}
+
+#[test]
+fn args_scoped_to_fun() {
+    assert!(analyze("funktion f het N8 a { }; tuen schreie mit a;").is_err());
+}
+
+#[test]
+fn annotates_types() {
+    let toks = Lexer::new("dä x isch 1 als Z8; dä y isch x plus 2;")
+        .lex()
+        .0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    SemanticAnalyzer::new(&ast)
+        .analyze()
+        .expect("failed to analyze");
+    let Stmt::VarAss(y) = &ast[1] else {
+        panic!("expected VarAss");
+    };
+    assert_eq!(y.value.ty.get(), Some(PrimType::Z8));
+    let ExprKind::Bin(bin) = &y.value.kind else {
+        panic!("expected Bin");
+    };
+    assert_eq!(bin.lhs.ty.get(), Some(PrimType::Z8));
+    assert_eq!(bin.rhs.ty.get(), Some(PrimType::Z8));
+}
+
+#[test]
+fn resolves_ids() {
+    let toks = Lexer::new(
+        "funktion f het N8 x git N8 { gib x; }; dä x isch wahr; dä x isch tuen f mit 1; dä y isch x;",
+    )
+    .lex().0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    let typed = SemanticAnalyzer::new(&ast)
+        .analyze()
+        .expect("failed to analyze");
+    let Stmt::VarAss(y) = &ast[3] else {
+        panic!("expected VarAss");
+    };
+    let ExprKind::Prim(Prim::Id(_, decl)) = &y.value.kind else {
+        panic!("expected Id");
+    };
+    let Stmt::VarAss(x) = &ast[2] else {
+        panic!("expected VarAss");
+    };
+    // the second `x` shadows the first one
+    assert_eq!(decl.get(), x.decl.get());
+    let decl = typed.decl(decl.get().expect("unresolved id"));
+    assert_eq!(decl.id, "x");
+    assert!(matches!(
+        decl.symbol,
+        Symbol::Var {
+            pt: PrimType::N8,
+            mutable: false
+        }
+    ));
+    let ExprKind::StEx(StEx::Call(call)) = &x.value.kind else {
+        panic!("expected Call");
+    };
+    let Stmt::FunAss(f) = &ast[0] else {
+        panic!("expected FunAss");
+    };
+    assert_eq!(call.decl.get(), f.decl.get());
+}
+
+#[test]
+fn hoisting() {
+    assert!(
+        analyze("funktion f git N8 { gib tuen g mit; }; funktion g git N8 { gib 1; };").is_ok()
+    );
+    assert!(analyze(
+        "funktion grad het N8 n git Wahrheit { gib wahr wenn n gliich 0 suscht tuen ungrad mit n minus 1; }; \
+         funktion ungrad het N8 n git Wahrheit { gib falsch wenn n gliich 0 suscht tuen grad mit n minus 1; };"
+    )
+    .is_ok());
+    let err = analyze("funktion f { }; tuen f mit; funktion f git N8 { gib 1; };")
+        .expect_err("f is declared twice");
+    assert!(
+        err.contains("at row 1 col 29 :) Function `f` is already declared"),
+        "{}",
+        err
+    );
+    // top level variables are set in order, a hoisted function can't read one before that
+    let err = analyze(
+        "dä y isch tuen f mit; dä x isch 5; funktion f git N8 { gib tuen g mit; }; \
+         funktion g git N8 { gib x; };",
+    )
+    .expect_err("x is read before it's set");
+    assert!(
+        err.contains("at row 1 col 11 :) Call reads `x` before it's set"),
+        "{}",
+        err
+    );
+    assert!(analyze("dä x isch 5; dä y isch tuen f mit; funktion f git N8 { gib x; };").is_ok());
+    assert!(analyze(
+        "funktion f git N8 { gib tuen g mit; }; funktion g git N8 { gib tuen f mit; }; \
+         dä y isch tuen f mit;"
+    )
+    .is_ok());
+    // nested functions shadow like variables
+    assert!(analyze("funktion f { funktion g { }; funktion g { }; };").is_ok());
+    // only top level functions are hoisted
+    assert!(analyze("funktion f { tuen g mit; funktion g { }; };").is_err());
+    assert!(analyze("tuen schreie mit x; dä x isch 1;").is_err());
+}
+
+#[test]
+fn error_spans() {
+    let err = analyze("dä x isch 1;\ndä y isch x plus z;").expect_err("z isn't defined");
+    assert!(err.contains("at row 2 col 18 "), "{}", err);
+    let err = analyze("funktion f het N8 a, N8 a { };").expect_err("duplicate args");
+    assert!(err.contains("at row 1 col 22 "), "{}", err);
+}
+
+#[test]
+fn reports_all_errors() {
+    let err = analyze(
+        "dä x isch y;\nfunktion f { hör uf; gib 1; };\nfunktion g git N8 { gib wahr; };\nz wird 1;",
+    )
+    .expect_err("expected errors");
+    assert_eq!(err.lines().count(), 5, "{}", err);
+    for (line, row) in err.lines().zip([1, 2, 2, 3, 4]) {
+        assert!(line.contains(&format!("at row {} ", row)), "{}", line);
+    }
+}
+
+#[test]
+fn no_follow_up_errors() {
+    // `x` is still declared with its explicit type
+    assert_eq!(
+        analyze("dä x isch wahr als N8; dä y isch x plus 1;")
+            .expect_err("expected an error")
+            .lines()
+            .count(),
+        1
+    );
+    // without `als` the type of `x` is unknown, its uses aren't reported again
+    assert_eq!(
+        analyze("funktion f { dä x isch y; dä z isch x plus 1; tuen schreie mit z; z wird 2; };"),
+        Err(
+            "Your code is semantically incorrect at row 1 col 24 :) Token `y` not defined when assigning to `x`"
+                .to_string()
+        )
+    );
+    assert_eq!(
+        analyze("funktion f { dä x isch y; dä z isch { gib x; }; tuen schreie mit z; };")
+            .expect_err("expected an error")
+            .lines()
+            .count(),
+        1
+    );
+    // an error inside a block value is only reported once
+    assert_eq!(
+        analyze("dä x isch { gib y; };")
+            .expect_err("expected an error")
+            .lines()
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn max_errors() {
+    let toks = Lexer::new("x wird 1; y wird 1; z wird 1;").lex().0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    let errs = SemanticAnalyzer::new(&ast)
+        .max_errors(2)
+        .analyze()
+        .map(|_| ())
+        .expect_err("expected errors");
+    assert_eq!(errs.len(), 2);
+}
+
+#[test]
+fn literal_ranges() {
+    assert!(analyze("dä a isch 255; dä b isch -128; dä c isch 0x7f als Z8;").is_ok());
+    assert!(analyze("dä a isch 256;").is_err());
+    assert!(analyze("dä a isch 128 als Z8;").is_err());
+    assert!(analyze("dä a isch -1 als N8;").is_err());
+    assert!(analyze("dä a isch 1000 als R8; dä b isch 1.5e300;").is_ok());
+    assert!(analyze("dä a isch 1e400;").is_err());
+    // the literal takes on the type of the other operand
+    assert!(analyze("funktion f het Z8 a git Z8 { gib a plus 200; };").is_err());
+    assert_eq!(
+        analyze("dä a isch 300;"),
+        Err(
+            "Your code is semantically incorrect at row 1 col 11 :) Number is out of range for N8"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn float_literals() {
+    assert!(analyze("dä a isch 1.5; dä b isch a plus 2; dä c isch 2 mal 0.5;").is_ok());
+    assert!(analyze("dä a isch 1.5 als N8;").is_err());
+    assert!(analyze("funktion f het N8 a git N8 { gib a plus 0.5; };").is_err());
+}
+
+#[test]
+fn fixed_width_types() {
+    assert!(analyze("dä a isch 65535 als N16; dä b isch -2147483648 als Z32; dä c isch 0xffff_ffff_ffff_ffff als N64;").is_ok());
+    assert!(analyze("dä a isch 65536 als N16;").is_err());
+    assert!(analyze("dä a isch 9223372036854775808 als Z64;").is_err());
+    assert!(analyze("dä a isch 3.4e38 als R4; dä b isch 1 als R4;").is_ok());
+    assert!(analyze("dä a isch 3.5e38 als R4;").is_err());
+    // different widths don't mix
+    assert!(analyze("funktion f het N16 a, N8 b git N16 { gib a plus b; };").is_err());
+    assert!(analyze("funktion f het N16 a git N16 { gib a plus 1000; };").is_ok());
+}
+
+#[test]
+fn for_bound_types() {
+    assert!(analyze(
+        "funktion f het Z64 n { für i vo 0 bis n schritt 2 { dä j isch i als Z64; }; };"
+    )
+    .is_ok());
+    assert!(analyze("funktion f { für i vo -5 bis 5 { dä j isch i als Z8; }; };").is_ok());
+    assert!(analyze("funktion f het N8 a, Z8 b { für i vo a bis b { }; };").is_err());
+    assert!(analyze("funktion f het R4 a { für i vo 0 bis a { }; };").is_err());
+}
+
+#[test]
+fn casts() {
+    assert!(analyze(
+        "funktion f het N8 a, Z64 b, R4 c, Wahrheit d git R8 { \
+         dä e isch a als Z64 plus b; dä g isch c als N8 plus a; dä h isch d als N8 plus a; \
+         dä s isch (b als Zeiche); dä t isch (d als Zeiche); dä u isch (300 als N16); \
+         gib a als R8 durch 2; };"
+    )
+    .is_ok());
+    assert!(analyze("dä a isch (\"5\" als N8);").is_err());
+    assert!(analyze("dä a isch (5 als Wahrheit);").is_err());
+    assert!(analyze("dä a isch (-1 als N8);").is_err());
+    assert_eq!(
+        analyze("dä a isch 1; dä b isch (a als Wahrheit);"),
+        Err(
+            "Your code is semantically incorrect at row 1 col 25 :) Can't convert N8 to Wahrheit with `als`"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn un() {
+    assert!(analyze(
+        "funktion f het Z16 a, R4 b, Wahrheit c git Wahrheit { \
+         dä d isch minus a plus 1; dä e isch minus b; dä g isch minus 128 als Z8; \
+         dä h isch minus 5; gib nöd c oder nöd a gliich d; };"
+    )
+    .is_ok());
+    assert!(analyze("dä a isch minus 129 als Z8;").is_err());
+    assert_eq!(
+        analyze("dä a isch 1; dä b isch minus a;"),
+        Err(
+            "Your code is semantically incorrect at row 1 col 30 :) Operator `minus` can't be used with N8"
+                .to_string()
+        )
+    );
+    assert_eq!(
+        analyze("dä a isch nöd 1;"),
+        Err(
+            "Your code is semantically incorrect at row 1 col 15 :) Operator `nöd` can't be used with N8"
+                .to_string()
+        )
+    );
+}
@@ -0,0 +1,295 @@
+use haessig::{
+    lexer::Lexer,
+    parser::Parser,
+    seman::SemanticAnalyzer,
+    trans::{Overflow, Transpiler},
+};
+
+fn transpile(input: &str) -> String {
+    transpile_with(input, Overflow::default())
+}
+
+fn transpile_with(input: &str, overflow: Overflow) -> String {
+    let toks = Lexer::new(input).lex().0;
+    let ast = Parser::new(&toks).parse().expect("failed to parse");
+    let typed = match SemanticAnalyzer::new(&ast).analyze() {
+        Ok(typed) => typed,
+        Err(e) => panic!("failed to analyze: {:?}", e),
+    };
+    Transpiler::new(&typed).overflow(overflow).generate()
+}
+
+#[test]
+fn var_ass_inferred() {
+    let c = transpile(
+        "funktion f het Z8 a git Wahrheit { dä b isch a mal 2; dä c isch \"c\"; dä d isch b grösser 1; gib d; };",
+    );
+    assert!(c.contains("\nint8_t const b__2 = haessig_mal_z8(a__1, 2, 1, 46);"));
+    assert!(c.contains("char* const c__3 = \"c\";"));
+    assert!(c.contains("\nint const d__4 = (b__2 > 1);"));
+}
+
+#[test]
+fn var_ass_inferred_call() {
+    let c = transpile("funktion f git R8 { gib 1; }; funktion g { dä x isch tuen f mit; };");
+    assert!(c.contains("double const x__2 = f__0();"));
+}
+
+#[test]
+fn var_ass_literal_default() {
+    let c = transpile("dä x isch 5; dä veränderlich y isch 5 als Z8;");
+    assert!(c.contains("uint8_t x__0;\nint8_t y__1;\n"));
+    assert!(c.contains("x__0 = 5;\ny__1 = 5;\n"));
+}
+
+#[test]
+fn schreie_formats() {
+    let c = transpile(
+        "dä a isch 1; dä b isch 1 als Z8; dä c isch 1 als R8; dä d isch wahr; dä e isch \"e\"; \
+         funktion chuchichäschtli { tuen schreie mit a, b, c, d, e; };",
+    );
+    assert!(c.contains(
+        "printf(\"%llu %lld %g %s %s\\n\", (unsigned long long)(a__1), (long long)(b__2), c__3, \
+         (d__4) ? \"wahr\" : \"falsch\", e__5);"
+    ));
+}
+
+#[test]
+fn schreie_user_defined() {
+    let c = transpile("funktion schreie het N8 a { }; funktion g { tuen schreie mit 1; };");
+    assert!(c.contains("schreie__0(1);"));
+}
+
+#[test]
+fn string_eq() {
+    let c = transpile("dä a isch \"a\"; dä b isch a gliich \"b\"; dä c isch a ungliich a;");
+    assert!(c.contains("(strcmp(a__0, \"b\") == 0)"));
+    assert!(c.contains("(strcmp(a__0, a__0) != 0)"));
+}
+
+#[test]
+fn prototypes() {
+    let c = transpile(
+        "funktion chuchichäschtli { tuen f mit 1; }; funktion f het N8 a git Z8 { gib 1; };",
+    );
+    let proto = c
+        .find("int8_t f__1(uint8_t a__2);")
+        .expect("missing prototype");
+    assert!(proto < c.find("main()").expect("missing main"));
+    assert!(!c.contains("main();"));
+}
+
+#[test]
+fn string_escapes() {
+    let c = transpile(r#"dä s isch "sä \"hoi\"\n\t\\ \u{7}1";"#);
+    assert!(c.contains(r#"s__0 = "sä \"hoi\"\n\t\\ \0071";"#), "{}", c);
+}
+
+#[test]
+fn number_literals() {
+    let c =
+        transpile("dä a isch 0xff; dä b isch 3.0; dä c isch 5 durch 2 als R8; dä d isch 1e300;");
+    assert!(c.contains("a__0 = 255;"));
+    assert!(c.contains("b__1 = 3.0;"));
+    assert!(c.contains("c__2 = (5.0 / 2.0);"));
+    assert!(c.contains("d__3 = 1e300;"));
+}
+
+#[test]
+fn fixed_width_types() {
+    let c = transpile(
+        "dä a isch 18446744073709551615 als N64; dä b isch -9223372036854775808 als Z64; \
+         dä c isch 1.5 als R4; funktion f het N16 x, Z32 y git Z32 { gib y durch 2; };",
+    );
+    assert!(c.contains("#include <stdint.h>\n"));
+    assert!(c.contains("a__1 = 18446744073709551615u;"));
+    assert!(c.contains("b__2 = INT64_MIN;"));
+    assert!(c.contains("float c__3;"));
+    assert!(c.contains("c__3 = 1.5f;"));
+    assert!(c.contains("int32_t f__0(uint16_t x__4, int32_t y__5)"));
+    assert!(c.contains("return haessig_durch_z32(y__5, 2, 1, 145);"));
+}
+
+#[test]
+fn overflow_helpers() {
+    let input = "funktion f het N8 a, Z16 b { dä c isch a plus 1; dä d isch b hoch 2; };";
+    let c = transpile_with(input, Overflow::Wrap);
+    assert!(c.contains("uint8_t const c__3 = haessig_plus_n8(a__1, 1, 1, 40);"));
+    assert!(c.contains("int16_t const d__4 = haessig_hoch_z16(b__2, 2, 1, 60);"));
+    assert!(c.contains("static uint8_t haessig_plus_n8(uint8_t a, uint8_t b, int row, int col) {"));
+    assert!(c.contains("static int16_t haessig_hoch_z16(int16_t a, int16_t b, int row, int col) {"));
+    assert!(c.contains("__builtin_add_overflow(a, b, &r);\nreturn r;"));
+    // only the helpers used are generated
+    assert!(!c.contains("haessig_minus_n8"));
+    assert!(!c.contains("haessig_overflow"));
+
+    let c = transpile_with(input, Overflow::Trap);
+    assert!(c.contains("static void haessig_overflow("));
+    assert!(c.contains("haessig_overflow(\"plus\", \"N8\", row, col);"));
+    assert!(c.contains("haessig_overflow(\"hoch\", \"Z16\", row, col);"));
+
+    let c = transpile_with(input, Overflow::Saturate);
+    assert!(c.contains("return 1 ? UINT8_MAX : 0;"));
+    assert!(c.contains("return !neg ? INT16_MAX : INT16_MIN;"));
+}
+
+#[test]
+fn float_hoch() {
+    let c = transpile("dä a isch 2.0 hoch 0.5; dä b isch 2.0 als R4; dä c isch b hoch 2;");
+    assert!(c.contains("a__0 = pow(2.0, 0.5);"));
+    assert!(c.contains("c__2 = powf(b__1, 2.0f);"));
+}
+
+#[test]
+fn casts() {
+    let c = transpile(
+        "funktion f het N8 a, R8 b, Wahrheit d { dä e isch a als R8 durch b; dä g isch (b als Z16); \
+         dä h isch (d als Z8); dä s isch (a als Zeiche); dä t isch (d als Zeiche); \
+         dä u isch (b als Zeiche); dä v isch (a als N8); };",
+    );
+    assert!(c.contains("double const e__4 = (((double)(a__1)) / b__2);"));
+    assert!(c.contains("int16_t const g__5 = haessig_als_z16(b__2);"));
+    assert!(c.contains("static int16_t haessig_als_z16(double v) {"));
+    assert!(c.contains("int8_t const h__6 = ((int8_t)(d__3));"));
+    assert!(c.contains("char* const s__7 = haessig_zeiche_n(a__1);"));
+    assert!(c.contains("static char* haessig_zeiche_n(unsigned long long v) {"));
+    assert!(c.contains("char* const t__8 = ((d__3) ? \"wahr\" : \"falsch\");"));
+    assert!(c.contains("char* const u__9 = haessig_zeiche_r(b__2);"));
+    assert!(c.contains("uint8_t const v__10 = a__1;"));
+    assert!(!c.contains("haessig_zeiche_z"));
+}
+
+#[test]
+fn un() {
+    let c = transpile(
+        "funktion f het Z8 a, R8 b, Wahrheit c { dä d isch minus a; dä e isch minus b; \
+         dä g isch nöd c; dä h isch minus 128 als Z8; };",
+    );
+    assert!(c.contains("int8_t const d__4 = haessig_minus_z8(0, a__1, 1, 57);"));
+    assert!(c.contains("double const e__5 = (-b__2);"));
+    assert!(c.contains("int const g__6 = (!c__3);"));
+    assert!(c.contains("int8_t const h__7 = (-128);"));
+}
+
+#[test]
+fn shadowing() {
+    // every declaration gets its own C name, so the new `g` is initialised from the old one
+    let c = transpile(
+        "dä g isch 3; funktion f { dä veränderlich g isch g plus 5 als N8; g wird g mal 2; \
+         { dä g isch wahr; }; };",
+    );
+    assert!(c.contains("g__1 = 3;"));
+    assert!(c.contains("\nuint8_t g__2 = haessig_plus_n8(g__1, 5, 1, 50);"));
+    assert!(c.contains("g__2 = haessig_mal_n8(g__2, 2, 1, 74);"));
+    assert!(c.contains("int const g__3 = 1;"));
+}
+
+#[test]
+fn block_value() {
+    let c = transpile(
+        "funktion f het N8 n git N8 { dä x isch { wenn n grösser 3 { gib n; }; gib 1; }; \
+         { dä y isch x; }; gib x; };",
+    );
+    // `gib` inside the block value leaves the block, not the function
+    assert!(c.contains(
+        "uint8_t const x__2 = ({\nuint8_t haessig_wert_0 = 0;\nif ((n__1 > 3)) {\n{\n\
+         haessig_wert_0 = n__1;\ngoto haessig_gib_0;\n};\n}\n{\nhaessig_wert_0 = 1;\n\
+         goto haessig_gib_0;\n};\nhaessig_gib_0:;\nhaessig_wert_0;\n});"
+    ));
+    assert!(c.contains("{\nuint8_t const y__3 = x__2;\n};"));
+    assert!(c.contains("return x__2;"));
+}
+
+#[test]
+fn for_loop() {
+    let c = transpile(
+        "funktion f het Z8 s { für k vo 250 bis 255 schritt 3 { mach wiiter; }; \
+         für i vo 0 bis 3 als Z8 schritt s { }; };",
+    );
+    // the last step stops at `bis`, so `k` can't wrap around
+    assert!(c.contains(
+        "{\nuint8_t k__2 = 250;\nuint8_t const k__2__bis = 255;\nuint8_t const k__2__schritt = 3;\n\
+         for (; k__2 < k__2__bis; k__2 = (uint64_t)k__2__bis - (uint64_t)k__2 > \
+         (uint64_t)k__2__schritt ? k__2 + k__2__schritt : k__2__bis) {\ncontinue;\n}\n}"
+    ));
+    assert!(!c.contains("if (k__2__schritt <= 0)"));
+    assert!(c.contains(
+        "int8_t const i__3__schritt = s__1;\nif (i__3__schritt <= 0) {\n\
+         haessig_schritt(1, 104);\n}\n"
+    ));
+    assert!(c.contains("static void haessig_schritt(int row, int col) {"));
+}
+
+#[test]
+fn globals() {
+    // file scope initializers have to be constant, so globals are set at the start of `main`
+    let c = transpile(
+        "dä a isch 5 plus 3; dä b isch { gib a; }; funktion chuchichäschtli { tuen schreie mit b; };",
+    );
+    assert!(c.contains("uint8_t a__1;\nuint8_t b__2;\n"));
+    assert!(c.contains(
+        "int main() {\na__1 = haessig_plus_n8(5, 3, 1, 11);\nb__2 = ({\nuint8_t haessig_wert_0"
+    ));
+    // without `chuchichäschtli` they're set by a `main` of their own
+    let c = transpile("dä a isch 5 plus 3;");
+    assert!(c.contains("int main() {\na__0 = haessig_plus_n8(5, 3, 1, 11);\nreturn 0;\n}"));
+}
+
+#[test]
+fn top_level_statements() {
+    let c = transpile(
+        "dä veränderlich x isch 1; x wird 2; wenn x grösser 1 { x wird 3; }; \
+         für i vo 0 bis 2 { tuen schreie mit i; }; funktion chuchichäschtli { tuen schreie mit x; };",
+    );
+    let init =
+        c.find("x__1 = 1;\nx__1 = 2;\nif ((x__1 > 1)) {\nx__1 = 3;\n}\n{\nuint8_t i__2 = 0;");
+    let body = c.find("printf(\"%llu\\n\", (unsigned long long)(x__1));");
+    assert!(init.is_some() && init < body, "{}", c);
+}
+
+#[test]
+fn c_names() {
+    // functions are renamed like variables, so they don't clash with the C library
+    let c = transpile(
+        "funktion exit het N8 a git N8 { gib a; }; dä a-b isch 4; dä c' isch tuen exit mit a-b; \
+         funktion chuchichäschtli { tuen chuchichäschtli mit; };",
+    );
+    assert!(c.contains("uint8_t exit__0(uint8_t a__2);"));
+    assert!(c.contains("uint8_t a_b__3;"));
+    assert!(c.contains("c___4 = exit__0(a_b__3);"));
+    assert!(c.contains("\nmain();"));
+}
+
+#[test]
+fn division_helpers() {
+    let input = "funktion f het Z8 a, N8 b { dä c isch a durch 2; dä d isch b rescht 3; };";
+    let c = transpile_with(input, Overflow::Wrap);
+    assert!(c.contains("int8_t const c__3 = haessig_durch_z8(a__1, 2, 1, 39);"));
+    assert!(c.contains("uint8_t const d__4 = haessig_rescht_n8(b__2, 3, 1, 60);"));
+    // division by zero stops the program in every mode
+    assert!(c.contains("if (b == 0) {\nhaessig_durch_null(row, col);\n}\n"));
+    assert!(c.contains("if (a == INT8_MIN && b == -1) {\nreturn INT8_MIN;\n}\nreturn a / b;"));
+    assert!(c.contains("{\nhaessig_durch_null(row, col);\n}\nreturn a % b;"));
+    assert!(!c.contains("haessig_overflow("));
+
+    let c = transpile_with(input, Overflow::Trap);
+    assert!(c.contains(
+        "if (a == INT8_MIN && b == -1) {\nhaessig_overflow(\"durch\", \"Z8\", row, col);"
+    ));
+    let c = transpile_with(input, Overflow::Saturate);
+    assert!(c.contains("if (a == INT8_MIN && b == -1) {\nreturn INT8_MAX;"));
+}
+
+#[test]
+fn r4_literals() {
+    let c = transpile("dä x isch 0.1 als R4; dä y isch x gliich 0.1; dä z isch x plus 1;");
+    assert!(c.contains("x__0 = 0.1f;"));
+    assert!(c.contains("y__1 = (x__0 == 0.1f);"));
+    assert!(c.contains("z__2 = (x__0 + 1.0f);"));
+}
+
+#[test]
+fn main_returns_int() {
+    let c = transpile("funktion chuchichäschtli git Z8 { gib 0; };");
+    assert!(c.contains("int main() {"));
+}